@@ -0,0 +1,149 @@
+use std::borrow::Cow;
+use std::collections::BTreeMap;
+use crate::ParseError;
+use crate::document::{EntryTokens, LineKind, LineTokens, check_entry_value, tokenize_entry};
+use crate::key::normalize_key;
+use crate::options::ParserOptions;
+use crate::parser::DuplicatePolicy;
+use crate::stream::LineAssembler;
+
+/// 入力を借用したパース結果
+///
+/// キーと値は入力の部分文字列を指す。エスケープの展開・キーの正規化・継続行の結合が
+/// 必要な場合だけ新しい文字列を持つ。
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct BorrowedConfig<'a> {
+    values: BTreeMap<Cow<'a, str>, Cow<'a, str>>,
+}
+
+impl<'a> BorrowedConfig<'a> {
+    pub fn get(&self, key: &str) -> Option<&str> {
+        self.values.get(key).map(|v| v.as_ref())
+    }
+
+    pub fn contains_key(&self, key: &str) -> bool {
+        self.values.contains_key(key)
+    }
+
+    pub fn len(&self) -> usize {
+        self.values.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.values.is_empty()
+    }
+
+    /// キーの順に (キー, 値) を返す
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.values.iter().map(|(k, v)| (k.as_ref(), v.as_ref()))
+    }
+
+    pub fn as_map(&self) -> &BTreeMap<Cow<'a, str>, Cow<'a, str>> {
+        &self.values
+    }
+
+    /// `parse_str` と同じ所有した BTreeMap に変換する
+    pub fn into_owned(self) -> BTreeMap<String, String> {
+        self.values
+            .into_iter()
+            .map(|(k, v)| (k.into_owned(), v.into_owned()))
+            .collect()
+    }
+}
+
+/// 文字列をコピーせずにパースする（結果は `parse_str` と同じ）
+pub fn parse_borrowed(input: &str) -> Result<BorrowedConfig<'_>, ParseError> {
+    parse_borrowed_with(input, &ParserOptions::default())
+}
+
+/// オプションを指定して、文字列をコピーせずにパースする（結果は `parse_str_with` と同じ）
+pub fn parse_borrowed_with<'a>(input: &'a str, options: &ParserOptions) -> Result<BorrowedConfig<'a>, ParseError> {
+    let mut config = BorrowedConfig::default();
+    // `DuplicatePolicy::Error` のときだけ、キーごとの最初の行番号を覚える
+    let mut first_lines: BTreeMap<Cow<'a, str>, usize> = BTreeMap::new();
+    let mut assembler = LineAssembler::new(options);
+
+    for raw in input.split_inclusive('\n') {
+        if let Some(line) = assembler.push_line(raw) {
+            let (line_number, line) = line?;
+            add_line(&mut config, &mut first_lines, line_number, line, options)?;
+        }
+    }
+    if let Some((line_number, line)) = assembler.finish_line() {
+        add_line(&mut config, &mut first_lines, line_number, Cow::Owned(line), options)?;
+    }
+
+    Ok(config)
+}
+
+/// 論理行を1つパースし、`duplicate_policy` に従って結果に加える
+fn add_line<'a>(
+    config: &mut BorrowedConfig<'a>,
+    first_lines: &mut BTreeMap<Cow<'a, str>, usize>,
+    line_number: usize,
+    line: Cow<'a, str>,
+    options: &ParserOptions,
+) -> Result<(), ParseError> {
+    let (key, value) = match line {
+        Cow::Borrowed(line) => {
+            let Some(tokens) = entry_tokens(line, line_number, options)? else {
+                return Ok(());
+            };
+            (entry_key(tokens.key, options), tokens.value.value)
+        }
+        // 継続行はつないだ行をパースし、キーと値は所有した文字列で持つ
+        Cow::Owned(joined) => {
+            let Some(tokens) = entry_tokens(&joined, line_number, options)? else {
+                return Ok(());
+            };
+            let key = entry_key(tokens.key, options).into_owned();
+            (Cow::Owned(key), Cow::Owned(tokens.value.value.into_owned()))
+        }
+    };
+
+    match options.duplicate_policy {
+        DuplicatePolicy::Error => {
+            if let Some(&first_line) = first_lines.get(&key) {
+                return Err(ParseError::DuplicateKey {
+                    key: key.into_owned(),
+                    first_line,
+                    line_number,
+                });
+            }
+            first_lines.insert(key.clone(), line_number);
+            config.values.insert(key, value);
+        }
+        DuplicatePolicy::FirstWins => {
+            config.values.entry(key).or_insert(value);
+        }
+        DuplicatePolicy::LastWins | DuplicatePolicy::CollectAll => {
+            config.values.insert(key, value);
+        }
+    }
+    Ok(())
+}
+
+/// 1行を分解して検証する。除外指定の行は `None`。
+fn entry_tokens<'a>(
+    line: &'a str,
+    line_number: usize,
+    options: &ParserOptions,
+) -> Result<Option<EntryTokens<'a>>, ParseError> {
+    match tokenize_entry(line, options) {
+        LineTokens::Entry(tokens) => {
+            check_entry_value(options, line_number, tokens.key, &tokens.value.value)?;
+            Ok(Some(tokens))
+        }
+        LineTokens::Other(LineKind::Invalid(content)) => Err(ParseError::InvalidLine { line_number, content }),
+        LineTokens::Other(_) => Ok(None),
+    }
+}
+
+/// 正規化が必要なキー（スラッシュ区切り）だけ新しい文字列にする
+fn entry_key<'a>(key: &'a str, options: &ParserOptions) -> Cow<'a, str> {
+    if options.normalize_keys && key.contains('/') {
+        Cow::Owned(normalize_key(key))
+    } else {
+        Cow::Borrowed(key)
+    }
+}
@@ -0,0 +1,155 @@
+use std::collections::BTreeMap;
+use indexmap::IndexMap;
+use crate::ParseError;
+use crate::value::FromConfValue;
+
+/// ファイルに書かれた順序（キーが最初に現れた順）を保持する設定値のマップ
+///
+/// 同じキーへの再代入は値だけを更新し、位置は最初に現れた場所のまま変わらない。
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Config {
+    values: IndexMap<String, String>,
+}
+
+impl Config {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// キーの値（文字列のまま）
+    pub fn get_str(&self, key: &str) -> Option<&str> {
+        self.values.get(key).map(String::as_str)
+    }
+
+    /// キーの値を型 `T` に変換して返す
+    ///
+    /// キーがなければ `ParseError::MissingKey`、変換できなければ `ParseError::InvalidValue`。
+    pub fn get<T: FromConfValue>(&self, key: &str) -> Result<T, ParseError> {
+        self.get_opt(key)?.ok_or_else(|| ParseError::MissingKey { key: key.to_string() })
+    }
+
+    /// キーがなければ `None` を返す `get`
+    pub fn get_opt<T: FromConfValue>(&self, key: &str) -> Result<Option<T>, ParseError> {
+        self.get_str(key)
+            .map(|value| {
+                T::from_conf_value(value).map_err(|reason| ParseError::InvalidValue {
+                    key: key.to_string(),
+                    value: value.to_string(),
+                    reason,
+                })
+            })
+            .transpose()
+    }
+
+    /// キーがなければ `default` を返す `get`（値が不正な場合はエラー）
+    pub fn get_or<T: FromConfValue>(&self, key: &str, default: T) -> Result<T, ParseError> {
+        Ok(self.get_opt(key)?.unwrap_or(default))
+    }
+
+    pub fn contains_key(&self, key: &str) -> bool {
+        self.values.contains_key(key)
+    }
+
+    /// 値を設定する。既存のキーなら位置を変えずに値だけを置き換え、古い値を返す。
+    pub fn insert(&mut self, key: impl Into<String>, value: impl Into<String>) -> Option<String> {
+        self.values.insert(key.into(), value.into())
+    }
+
+    /// キーを取り除く（残りのキーの順序は保たれる）
+    pub fn remove(&mut self, key: &str) -> Option<String> {
+        self.values.shift_remove(key)
+    }
+
+    pub fn len(&self) -> usize {
+        self.values.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.values.is_empty()
+    }
+
+    /// (キー, 値) を書かれた順に返す
+    pub fn iter(&self) -> indexmap::map::Iter<'_, String, String> {
+        self.values.iter()
+    }
+
+    /// キーを書かれた順に返す
+    pub fn keys(&self) -> impl Iterator<Item = &str> {
+        self.values.keys().map(String::as_str)
+    }
+
+    /// 従来の API と同じ BTreeMap（キーのアルファベット順）に変換する
+    pub fn to_btree_map(&self) -> BTreeMap<String, String> {
+        self.values.iter().map(|(k, v)| (k.clone(), v.clone())).collect()
+    }
+}
+
+impl From<Config> for BTreeMap<String, String> {
+    fn from(config: Config) -> Self {
+        config.values.into_iter().collect()
+    }
+}
+
+impl From<BTreeMap<String, String>> for Config {
+    fn from(map: BTreeMap<String, String>) -> Self {
+        map.into_iter().collect()
+    }
+}
+
+impl FromIterator<(String, String)> for Config {
+    fn from_iter<I: IntoIterator<Item = (String, String)>>(iter: I) -> Self {
+        Config {
+            values: iter.into_iter().collect(),
+        }
+    }
+}
+
+impl Extend<(String, String)> for Config {
+    fn extend<I: IntoIterator<Item = (String, String)>>(&mut self, iter: I) {
+        self.values.extend(iter);
+    }
+}
+
+impl IntoIterator for Config {
+    type Item = (String, String);
+    type IntoIter = indexmap::map::IntoIter<String, String>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.values.into_iter()
+    }
+}
+
+impl<'a> IntoIterator for &'a Config {
+    type Item = (&'a String, &'a String);
+    type IntoIter = indexmap::map::Iter<'a, String, String>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.values.iter()
+    }
+}
+
+/// `validate_with_schema` などが受け付ける設定値のマップ（`BTreeMap<String, String>` と `Config`）
+pub trait ConfigMap {
+    fn get_value(&self, key: &str) -> Option<&str>;
+    fn insert_value(&mut self, key: String, value: String);
+}
+
+impl ConfigMap for BTreeMap<String, String> {
+    fn get_value(&self, key: &str) -> Option<&str> {
+        self.get(key).map(String::as_str)
+    }
+
+    fn insert_value(&mut self, key: String, value: String) {
+        self.insert(key, value);
+    }
+}
+
+impl ConfigMap for Config {
+    fn get_value(&self, key: &str) -> Option<&str> {
+        self.get_str(key)
+    }
+
+    fn insert_value(&mut self, key: String, value: String) {
+        self.insert(key, value);
+    }
+}
@@ -1,7 +1,10 @@
 use std::collections::BTreeMap;
 use serde_json::{Map, Value};
+use crate::ParseError;
+use crate::key::SysctlKey;
+use crate::schema::SchemaEntry;
 
-/// フラットな BTreeMap<String, String> をネストされた JSON 構造に変換する。
+/// フラットな BTreeMap<String, String>（または `Config`）をネストされた JSON 構造に変換する。
 ///
 /// 例:
 /// "log.file" => "/var/log/console.log"
@@ -14,27 +17,476 @@ use serde_json::{Map, Value};
 ///     "name": "default.log"
 ///   }
 /// }
-pub fn flatten_to_nested_json(map: &BTreeMap<String, String>) -> Value {
+///
+/// キーは `SysctlKey` のセグメント単位で分割するため、`net.ipv4.conf.eth0/100.rp_filter`
+/// の `eth0/100` は `"eth0.100"` という1つのキーになる。
+///
+/// JSON のキーは入力の順に並ぶ。`Config` を渡せばファイルに書かれた順になる。
+/// `log = on` と `log.file = x` のように値とネストが衝突した場合は後に現れた方が残る
+/// （`ConflictStrategy::LastWins`）。衝突をエラーにする場合は `try_flatten_to_nested_json` を使う。
+pub fn flatten_to_nested_json<'a, I>(map: I) -> Value
+where
+    I: IntoIterator<Item = (&'a String, &'a String)>,
+{
+  let options = ConverterOptions::new().conflicts(ConflictStrategy::LastWins);
+  try_flatten_to_nested_json_with(map, &options).expect("last-wins conversion never fails")
+}
+
+/// `flatten_to_nested_json` と同じ変換で、値とネストの衝突を `ParseError::KeyConflict` として返す
+pub fn try_flatten_to_nested_json<'a, I>(map: I) -> Result<Value, ParseError>
+where
+    I: IntoIterator<Item = (&'a String, &'a String)>,
+{
+  try_flatten_to_nested_json_with(map, &ConverterOptions::default())
+}
+
+/// オプションの `ConflictStrategy` に従ってネストされた JSON 構造に変換する
+pub fn try_flatten_to_nested_json_with<'a, I>(map: I, options: &ConverterOptions) -> Result<Value, ParseError>
+where
+    I: IntoIterator<Item = (&'a String, &'a String)>,
+{
+  nest(map, options, |_, value| Ok(Value::String(value.to_string())))
+}
+
+/// スキーマの型に従って値を JSON の数値・真偽値・配列にしながらネストされた JSON 構造に変換する
+///
+/// スキーマにないキーの値は文字列のまま。型に合わない値は `ParseError::InvalidValue` になる。
+/// 値とネストの衝突はエラーになる（`try_flatten_to_nested_json` と同じ）。
+pub fn flatten_to_typed_json<'a, I>(map: I, schema: &BTreeMap<String, SchemaEntry>) -> Result<Value, ParseError>
+where
+    I: IntoIterator<Item = (&'a String, &'a String)>,
+{
+  flatten_to_typed_json_with(map, schema, &ConverterOptions::default())
+}
+
+/// オプションを指定して、スキーマの型に従ったネストされた JSON 構造に変換する
+pub fn flatten_to_typed_json_with<'a, I>(
+    map: I,
+    schema: &BTreeMap<String, SchemaEntry>,
+    options: &ConverterOptions,
+) -> Result<Value, ParseError>
+where
+    I: IntoIterator<Item = (&'a String, &'a String)>,
+{
+  nest(map, options, |key, value| match schema.get(key) {
+      Some(entry) => entry.typ.to_json(value).ok_or_else(|| ParseError::InvalidValue {
+          key: key.to_string(),
+          value: value.to_string(),
+          reason: format!("expected {:?}", entry.typ),
+      }),
+      None => Ok(Value::String(value.to_string())),
+  })
+}
+
+/// キーをセグメントで分割してネストし、各値を `leaf` で JSON の値に変換する
+fn nest<'a, I>(
+    map: I,
+    options: &ConverterOptions,
+    mut leaf: impl FnMut(&str, &str) -> Result<Value, ParseError>,
+) -> Result<Value, ParseError>
+where
+    I: IntoIterator<Item = (&'a String, &'a String)>,
+{
   let mut root = Map::new();
 
   for (full_key, value) in map {
-      let parts: Vec<&str> = full_key.split('.').collect();
+      let segments = options.split_key(full_key);
+      let (last, parents) = segments.split_last().expect("key has at least one segment");
       let mut current = &mut root;
 
-      for (i, part) in parts.iter().enumerate() {
-          if i == parts.len() - 1 {
-              // 最後のパート → 値を挿入
-              current.insert(part.to_string(), Value::String(value.clone()));
-          } else {
-              // 中間ノード → Value::Object を期待
-              current = current
-                  .entry(part.to_string())
-                  .or_insert_with(|| Value::Object(Map::new()))
-                  .as_object_mut()
-                  .expect("expected object while nesting JSON");
+      // 中間ノード → Value::Object を期待
+      for (i, part) in parents.iter().enumerate() {
+          let slot = current.entry(part.clone()).or_insert_with(|| Value::Object(Map::new()));
+          if !slot.is_object() {
+              match &options.conflicts {
+                  ConflictStrategy::Error => {
+                      return Err(ParseError::KeyConflict {
+                          key: full_key.clone(),
+                          conflicting_key: options.display_key(&segments[..=i]),
+                      });
+                  }
+                  ConflictStrategy::LastWins => *slot = Value::Object(Map::new()),
+                  ConflictStrategy::ReservedKey(reserved) => {
+                      let scalar = slot.take();
+                      *slot = Value::Object(Map::from_iter([(reserved.clone(), scalar)]));
+                  }
+              }
+          }
+          current = slot.as_object_mut().expect("slot was just made an object");
+      }
+
+      // 最後のパート → 値を挿入
+      let value = leaf(full_key, value)?;
+      match current.get_mut(last) {
+          Some(Value::Object(children)) => match &options.conflicts {
+              ConflictStrategy::Error => {
+                  return Err(ParseError::KeyConflict {
+                      key: full_key.clone(),
+                      conflicting_key: first_leaf_key(segments.clone(), children, options),
+                  });
+              }
+              ConflictStrategy::LastWins => {
+                  current.insert(last.clone(), value);
+              }
+              ConflictStrategy::ReservedKey(reserved) => {
+                  children.insert(reserved.clone(), value);
+              }
+          },
+          _ => {
+              current.insert(last.clone(), value);
           }
       }
   }
 
-  Value::Object(root)
-}
\ No newline at end of file
+  // ルート自身はキーが添字だけでもオブジェクトのまま残す
+  if options.array_indices {
+      root.values_mut().for_each(objects_to_arrays);
+  }
+  Ok(Value::Object(root))
+}
+
+/// キーが `0` から抜けなく並ぶ添字のオブジェクトを配列にする
+///
+/// 添字に抜けがあるオブジェクト（`ports.2` だけなど）はオブジェクトのまま残す。
+fn objects_to_arrays(value: &mut Value) {
+    let Value::Object(children) = value else {
+        return;
+    };
+    for child in children.values_mut() {
+        objects_to_arrays(child);
+    }
+
+    let indices: Option<Vec<usize>> = children.keys().map(|key| array_index(key)).collect();
+    let Some(max) = indices.and_then(|indices| indices.into_iter().max()) else {
+        return;
+    };
+    // キーは重複しないので、最大の添字が要素数より小さければ抜けがない
+    if max >= children.len() {
+        return;
+    }
+    let mut items = vec![Value::Null; children.len()];
+    for (key, child) in std::mem::take(children) {
+        items[array_index(&key).expect("all keys are indices")] = child;
+    }
+    *value = Value::Array(items);
+}
+
+/// 配列の添字として扱うセグメント（先頭に `0` が付かない10進数）
+fn array_index(segment: &str) -> Option<usize> {
+    let canonical = !segment.is_empty()
+        && segment.bytes().all(|b| b.is_ascii_digit())
+        && (segment == "0" || !segment.starts_with('0'));
+    if canonical { segment.parse().ok() } else { None }
+}
+
+/// オブジェクトの下で最初に見つかる値のキー（衝突したキーの表示用）
+fn first_leaf_key(mut segments: Vec<String>, children: &Map<String, Value>, options: &ConverterOptions) -> String {
+    let mut current = children;
+    while let Some((key, child)) = current.iter().next() {
+        segments.push(key.clone());
+        match child {
+            Value::Object(grandchildren) => current = grandchildren,
+            _ => break,
+        }
+    }
+    options.display_key(&segments)
+}
+
+/// 値とネストが衝突したとき（`log = on` と `log.file = x`）の扱い
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub enum ConflictStrategy {
+    /// `ParseError::KeyConflict` を返す
+    #[default]
+    Error,
+    /// 値を指定した予約キー（`"_value"` など）に入れてネストと共存させる
+    ReservedKey(String),
+    /// 後に現れた方で上書きする
+    LastWins,
+}
+
+/// 配列の書き出し方
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ArrayPolicy {
+    /// スカラーの配列を空白区切りの1つの値にする（`tcp_rmem = 4096 87380 6291456`）
+    #[default]
+    SpaceJoined,
+    /// 要素ごとに添字のキーを作る（`servers.0.host = a`）
+    Indexed,
+}
+
+/// `null` の扱い
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum NullPolicy {
+    /// キーを書き出さない
+    #[default]
+    Skip,
+    /// 空の値として書き出す
+    Empty,
+    /// `ParseError::InvalidValue` を返す
+    Error,
+}
+
+/// JSON とフラットなマップを相互に変換するときのオプション
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConverterOptions {
+    pub(crate) arrays: ArrayPolicy,
+    pub(crate) nulls: NullPolicy,
+    pub(crate) bools_as_words: bool,
+    pub(crate) conflicts: ConflictStrategy,
+    pub(crate) separator: Option<String>,
+    pub(crate) escape: Option<char>,
+    pub(crate) array_indices: bool,
+}
+
+impl Default for ConverterOptions {
+    fn default() -> Self {
+        ConverterOptions {
+            arrays: ArrayPolicy::default(),
+            nulls: NullPolicy::default(),
+            bools_as_words: true,
+            conflicts: ConflictStrategy::default(),
+            separator: None,
+            escape: None,
+            array_indices: false,
+        }
+    }
+}
+
+impl ConverterOptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 配列の書き出し方（デフォルト: 空白区切り）
+    pub fn arrays(mut self, policy: ArrayPolicy) -> Self {
+        self.arrays = policy;
+        self
+    }
+
+    /// `null` の扱い（デフォルト: 書き出さない）
+    pub fn nulls(mut self, policy: NullPolicy) -> Self {
+        self.nulls = policy;
+        self
+    }
+
+    /// 値とネストが衝突したときの扱い（デフォルト: エラー）
+    pub fn conflicts(mut self, strategy: ConflictStrategy) -> Self {
+        self.conflicts = strategy;
+        self
+    }
+
+    /// 真偽値を `true` / `false` で書き出すか（デフォルト: true。false なら sysctl と同じ `1` / `0`）
+    pub fn bools_as_words(mut self, enabled: bool) -> Self {
+        self.bools_as_words = enabled;
+        self
+    }
+
+    /// キーのセグメントの区切り（`/` や `__` など）
+    ///
+    /// 指定しない場合は `SysctlKey` と同じ規則（`.` 区切り、セグメント内の `.` は `/`）。
+    pub fn separator(mut self, separator: impl Into<String>) -> Self {
+        self.separator = Some(separator.into());
+        self
+    }
+
+    /// セグメント内の区切り文字を表すエスケープ文字（例: `\` で `a\.b.c` → `"a.b"`, `"c"`）
+    ///
+    /// 区切りを指定していなければ `.` 区切りになる。エスケープ文字自身は2つ重ねて書く。
+    pub fn escape(mut self, escape: char) -> Self {
+        self.escape = Some(escape);
+        self
+    }
+
+    /// 添字だけのセグメント（`servers.0.host` の `0`）を JSON の配列として扱うか（デフォルト: false）
+    pub fn array_indices(mut self, enabled: bool) -> Self {
+        self.array_indices = enabled;
+        self
+    }
+
+    /// `SysctlKey` の規則を使うか（区切りもエスケープも指定されていない）
+    fn uses_sysctl_keys(&self) -> bool {
+        self.separator.is_none() && self.escape.is_none()
+    }
+
+    fn separator_str(&self) -> &str {
+        self.separator.as_deref().unwrap_or(".")
+    }
+
+    /// キーをセグメントに分割する
+    pub(crate) fn split_key(&self, key: &str) -> Vec<String> {
+        if self.uses_sysctl_keys() {
+            return SysctlKey::parse(key).segments().to_vec();
+        }
+        let separator = self.separator_str();
+        if separator.is_empty() {
+            return vec![key.to_string()];
+        }
+
+        let mut segments = Vec::new();
+        let mut current = String::new();
+        let mut rest = key;
+        while let Some(c) = rest.chars().next() {
+            if Some(c) == self.escape {
+                let after = &rest[c.len_utf8()..];
+                if let Some(tail) = after.strip_prefix(separator) {
+                    current.push_str(separator);
+                    rest = tail;
+                    continue;
+                }
+                if let Some(tail) = after.strip_prefix(c) {
+                    current.push(c);
+                    rest = tail;
+                    continue;
+                }
+            } else if let Some(tail) = rest.strip_prefix(separator) {
+                segments.push(std::mem::take(&mut current));
+                rest = tail;
+                continue;
+            }
+            current.push(c);
+            rest = &rest[c.len_utf8()..];
+        }
+        segments.push(current);
+        segments
+    }
+
+    /// セグメントをキーにつなぐ。区切りを含むセグメントをエスケープできない場合
+    /// （`SysctlKey` の規則では `/` を含むセグメント）は `None`。
+    pub(crate) fn join_key(&self, segments: &[String]) -> Option<String> {
+        if self.uses_sysctl_keys() {
+            let key = SysctlKey::from_segments(segments);
+            return key.is_representable().then(|| key.to_dotted());
+        }
+        let separator = self.separator_str();
+        let mut escaped = Vec::with_capacity(segments.len());
+        for segment in segments {
+            match self.escape {
+                Some(escape) => {
+                    let doubled = segment.replace(escape, &format!("{escape}{escape}"));
+                    escaped.push(doubled.replace(separator, &format!("{escape}{separator}")));
+                }
+                None if !separator.is_empty() && segment.contains(separator) => return None,
+                None => escaped.push(segment.clone()),
+            }
+        }
+        Some(escaped.join(separator))
+    }
+
+    /// エラー表示用のキー（エスケープできない場合もそのままつなぐ）
+    fn display_key(&self, segments: &[String]) -> String {
+        self.join_key(segments).unwrap_or_else(|| segments.join(self.separator_str()))
+    }
+}
+
+/// ネストされた JSON をフラットな BTreeMap<String, String> に戻す（`flatten_to_nested_json` の逆変換）
+///
+/// オブジェクトのキーはドット区切りでつなぎ、キーの中の `.` は `SysctlKey` と同じく `/` で表す
+/// （`ConverterOptions::separator` / `escape` で変更できる）。
+/// 数値はそのままの表記、真偽値は `true` / `false` になる。配列は空白区切り、`null` は読み飛ばす。
+pub fn nested_json_to_flat(value: &Value) -> Result<BTreeMap<String, String>, ParseError> {
+    nested_json_to_flat_with(value, &ConverterOptions::default())
+}
+
+/// オプションを指定して、ネストされた JSON をフラットな BTreeMap<String, String> に戻す
+pub fn nested_json_to_flat_with(
+    value: &Value,
+    options: &ConverterOptions,
+) -> Result<BTreeMap<String, String>, ParseError> {
+    let Value::Object(root) = value else {
+        return Err(ParseError::InvalidValue {
+            key: String::new(),
+            value: value.to_string(),
+            reason: "expected a JSON object at the top level".to_string(),
+        });
+    };
+
+    let mut flat = BTreeMap::new();
+    let mut path = Vec::new();
+    for (key, child) in root {
+        path.push(key.clone());
+        flatten_value(child, &mut path, options, &mut flat)?;
+        path.pop();
+    }
+    Ok(flat)
+}
+
+fn flatten_value(
+    value: &Value,
+    path: &mut Vec<String>,
+    options: &ConverterOptions,
+    flat: &mut BTreeMap<String, String>,
+) -> Result<(), ParseError> {
+    let key = || options.display_key(path);
+    // 書き出すキー（区切りを含むセグメントをエスケープできなければエラー）
+    let flat_key = || {
+        options.join_key(path).ok_or_else(|| ParseError::InvalidValue {
+            key: key(),
+            value: value.to_string(),
+            reason: if options.uses_sysctl_keys() {
+                "a key segment contains '/'; set a separator or an escape character".to_string()
+            } else {
+                format!("a key segment contains the separator '{}'; set an escape character", options.separator_str())
+            },
+        })
+    };
+
+    match value {
+        Value::Object(children) => {
+            for (child_key, child) in children {
+                path.push(child_key.clone());
+                flatten_value(child, path, options, flat)?;
+                path.pop();
+            }
+        }
+        Value::Array(items) if options.arrays == ArrayPolicy::Indexed => {
+            for (i, item) in items.iter().enumerate() {
+                path.push(i.to_string());
+                flatten_value(item, path, options, flat)?;
+                path.pop();
+            }
+        }
+        Value::Array(items) => {
+            let joined = items
+                .iter()
+                .map(|item| match scalar_text(item, options) {
+                    Some(text) if !text.is_empty() && !text.contains(char::is_whitespace) => Ok(text),
+                    _ => Err(ParseError::InvalidValue {
+                        key: key(),
+                        value: value.to_string(),
+                        reason: "only arrays of scalars without whitespace can be space-joined".to_string(),
+                    }),
+                })
+                .collect::<Result<Vec<_>, _>>()?
+                .join(" ");
+            flat.insert(flat_key()?, joined);
+        }
+        Value::Null => match options.nulls {
+            NullPolicy::Skip => {}
+            NullPolicy::Empty => {
+                flat.insert(flat_key()?, String::new());
+            }
+            NullPolicy::Error => {
+                return Err(ParseError::InvalidValue {
+                    key: key(),
+                    value: "null".to_string(),
+                    reason: "null is not allowed".to_string(),
+                });
+            }
+        },
+        scalar => {
+            flat.insert(flat_key()?, scalar_text(scalar, options).unwrap_or_default());
+        }
+    }
+    Ok(())
+}
+
+/// 文字列・数値・真偽値の表記（それ以外は `None`）
+fn scalar_text(value: &Value, options: &ConverterOptions) -> Option<String> {
+    match value {
+        Value::String(s) => Some(s.clone()),
+        Value::Number(n) => Some(n.to_string()),
+        Value::Bool(b) if options.bools_as_words => Some(b.to_string()),
+        Value::Bool(b) => Some(if *b { "1" } else { "0" }.to_string()),
+        _ => None,
+    }
+}
@@ -0,0 +1,295 @@
+use serde::de::value::StringDeserializer;
+use serde::de::{self, DeserializeOwned, DeserializeSeed, IntoDeserializer, MapAccess, SeqAccess, Visitor};
+use indexmap::IndexMap;
+use crate::ParseError;
+use crate::key::SysctlKey;
+use crate::parser::parse_config;
+use crate::value::FromConfValue;
+
+/// 文字列をパースし、ドット区切りのキーをネストした構造体としてデシリアライズする
+///
+/// `log.file = x` は `cfg.log.file` に入る。数値や真偽値のフィールドには値の文字列を
+/// `FromConfValue` と同じ規則で変換する。
+pub fn from_str<T: DeserializeOwned>(input: &str) -> Result<T, ParseError> {
+    from_map(&parse_config(input)?)
+}
+
+/// パース済みのマップ（`BTreeMap<String, String>` または `Config`）からデシリアライズする
+pub fn from_map<'a, T, I>(map: I) -> Result<T, ParseError>
+where
+    T: DeserializeOwned,
+    I: IntoIterator<Item = (&'a String, &'a String)>,
+{
+    T::deserialize(Node::from_flat(map)?)
+}
+
+/// キーのセグメントで組み立てた木
+enum Node {
+    /// 値（エラー表示のために元のキーも持つ）
+    Leaf { key: String, value: String },
+    Branch { path: String, children: IndexMap<String, Node> },
+}
+
+impl Node {
+    fn from_flat<'a, I>(map: I) -> Result<Node, ParseError>
+    where
+        I: IntoIterator<Item = (&'a String, &'a String)>,
+    {
+        let mut root = Node::Branch {
+            path: String::new(),
+            children: IndexMap::new(),
+        };
+
+        for (full_key, value) in map {
+            let key = SysctlKey::parse(full_key);
+            let (last, parents) = key.segments().split_last().expect("key has at least one segment");
+            let mut current = &mut root;
+
+            for part in parents {
+                let Node::Branch { path, children } = current else {
+                    return Err(conflict(full_key));
+                };
+                let child_path = join_path(path, part);
+                current = children.entry(part.clone()).or_insert_with(|| Node::Branch {
+                    path: child_path,
+                    children: IndexMap::new(),
+                });
+            }
+
+            let Node::Branch { children, .. } = current else {
+                return Err(conflict(full_key));
+            };
+            if matches!(children.get(last), Some(Node::Branch { .. })) {
+                return Err(conflict(full_key));
+            }
+            children.insert(
+                last.clone(),
+                Node::Leaf {
+                    key: full_key.clone(),
+                    value: value.clone(),
+                },
+            );
+        }
+
+        Ok(root)
+    }
+
+    /// `FromConfValue` で値を変換する
+    fn parse<T: FromConfValue>(self) -> Result<T, ParseError> {
+        match self {
+            Node::Leaf { key, value } => T::from_conf_value(&value).map_err(|reason| ParseError::InvalidValue {
+                key,
+                value,
+                reason,
+            }),
+            Node::Branch { path, .. } => Err(ParseError::Serde(format!("'{}' has nested keys, expected a value", path))),
+        }
+    }
+}
+
+fn join_path(parent: &str, part: &str) -> String {
+    if parent.is_empty() {
+        part.to_string()
+    } else {
+        format!("{}.{}", parent, part)
+    }
+}
+
+fn conflict(key: &str) -> ParseError {
+    ParseError::Serde(format!("'{}' is used both as a value and as a parent of nested keys", key))
+}
+
+macro_rules! deserialize_parsed {
+    ($($method:ident => $visit:ident: $t:ty),*) => {
+        $(
+            fn $method<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, ParseError> {
+                visitor.$visit(self.parse::<$t>()?)
+            }
+        )*
+    };
+}
+
+impl<'de> de::Deserializer<'de> for Node {
+    type Error = ParseError;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, ParseError> {
+        match self {
+            Node::Leaf { value, .. } => visitor.visit_string(value),
+            Node::Branch { children, .. } => visitor.visit_map(NodeMap::new(children)),
+        }
+    }
+
+    deserialize_parsed! {
+        deserialize_bool => visit_bool: bool,
+        deserialize_i8 => visit_i8: i8,
+        deserialize_i16 => visit_i16: i16,
+        deserialize_i32 => visit_i32: i32,
+        deserialize_i64 => visit_i64: i64,
+        deserialize_i128 => visit_i128: i128,
+        deserialize_u8 => visit_u8: u8,
+        deserialize_u16 => visit_u16: u16,
+        deserialize_u32 => visit_u32: u32,
+        deserialize_u64 => visit_u64: u64,
+        deserialize_u128 => visit_u128: u128,
+        deserialize_f32 => visit_f32: f32,
+        deserialize_f64 => visit_f64: f64
+    }
+
+    fn deserialize_char<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, ParseError> {
+        self.deserialize_string(visitor)
+    }
+
+    fn deserialize_str<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, ParseError> {
+        self.deserialize_string(visitor)
+    }
+
+    fn deserialize_string<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, ParseError> {
+        visitor.visit_string(self.parse::<String>()?)
+    }
+
+    fn deserialize_bytes<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, ParseError> {
+        self.deserialize_byte_buf(visitor)
+    }
+
+    fn deserialize_byte_buf<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, ParseError> {
+        visitor.visit_byte_buf(self.parse::<String>()?.into_bytes())
+    }
+
+    /// キーが存在すれば `Some`（キーがない場合は serde がフィールドを `None` にする）
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, ParseError> {
+        visitor.visit_some(self)
+    }
+
+    fn deserialize_unit<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, ParseError> {
+        visitor.visit_unit()
+    }
+
+    fn deserialize_unit_struct<V: Visitor<'de>>(self, _name: &'static str, visitor: V) -> Result<V::Value, ParseError> {
+        self.deserialize_unit(visitor)
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, ParseError> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    /// 値は空白区切りの配列、ネストしたキーは `0`, `1`, ... の添字順の配列として扱う
+    fn deserialize_seq<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, ParseError> {
+        match self {
+            Node::Leaf { key, value } => {
+                let items: Vec<Node> = value
+                    .split_whitespace()
+                    .map(|item| Node::Leaf {
+                        key: key.clone(),
+                        value: item.to_string(),
+                    })
+                    .collect();
+                visitor.visit_seq(NodeSeq(items.into_iter()))
+            }
+            Node::Branch { path, children } => {
+                let mut indexed = Vec::with_capacity(children.len());
+                for (index, child) in children {
+                    let index: usize = index.parse().map_err(|_| {
+                        ParseError::Serde(format!("'{}' has non-numeric key '{}', expected a sequence", path, index))
+                    })?;
+                    indexed.push((index, child));
+                }
+                indexed.sort_by_key(|(index, _)| *index);
+                visitor.visit_seq(NodeSeq(indexed.into_iter().map(|(_, child)| child).collect::<Vec<_>>().into_iter()))
+            }
+        }
+    }
+
+    fn deserialize_tuple<V: Visitor<'de>>(self, _len: usize, visitor: V) -> Result<V::Value, ParseError> {
+        self.deserialize_seq(visitor)
+    }
+
+    fn deserialize_tuple_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _len: usize,
+        visitor: V,
+    ) -> Result<V::Value, ParseError> {
+        self.deserialize_seq(visitor)
+    }
+
+    fn deserialize_map<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, ParseError> {
+        match self {
+            Node::Branch { children, .. } => visitor.visit_map(NodeMap::new(children)),
+            Node::Leaf { key, .. } => Err(ParseError::Serde(format!("'{}' is a value, expected nested keys", key))),
+        }
+    }
+
+    fn deserialize_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, ParseError> {
+        self.deserialize_map(visitor)
+    }
+
+    /// 値の文字列をユニットバリアント名として扱う
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, ParseError> {
+        let value: StringDeserializer<ParseError> = self.parse::<String>()?.into_deserializer();
+        visitor.visit_enum(value)
+    }
+
+    fn deserialize_identifier<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, ParseError> {
+        self.deserialize_string(visitor)
+    }
+
+    fn deserialize_ignored_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, ParseError> {
+        visitor.visit_unit()
+    }
+}
+
+struct NodeMap {
+    children: indexmap::map::IntoIter<String, Node>,
+    value: Option<Node>,
+}
+
+impl NodeMap {
+    fn new(children: IndexMap<String, Node>) -> Self {
+        NodeMap {
+            children: children.into_iter(),
+            value: None,
+        }
+    }
+}
+
+impl<'de> MapAccess<'de> for NodeMap {
+    type Error = ParseError;
+
+    fn next_key_seed<K: DeserializeSeed<'de>>(&mut self, seed: K) -> Result<Option<K::Value>, ParseError> {
+        let Some((key, value)) = self.children.next() else {
+            return Ok(None);
+        };
+        self.value = Some(value);
+        let key: StringDeserializer<ParseError> = key.into_deserializer();
+        seed.deserialize(key).map(Some)
+    }
+
+    fn next_value_seed<V: DeserializeSeed<'de>>(&mut self, seed: V) -> Result<V::Value, ParseError> {
+        let value = self.value.take().expect("next_value_seed called before next_key_seed");
+        seed.deserialize(value)
+    }
+}
+
+struct NodeSeq(std::vec::IntoIter<Node>);
+
+impl<'de> SeqAccess<'de> for NodeSeq {
+    type Error = ParseError;
+
+    fn next_element_seed<T: DeserializeSeed<'de>>(&mut self, seed: T) -> Result<Option<T::Value>, ParseError> {
+        self.0.next().map(|node| seed.deserialize(node)).transpose()
+    }
+}
@@ -0,0 +1,740 @@
+use std::borrow::Cow;
+use std::collections::BTreeMap;
+use std::ffi::OsString;
+use std::fmt;
+use std::fs;
+use std::io::{self, Write};
+use std::ops::Range;
+use std::path::Path;
+use crate::ParseError;
+use crate::config::Config;
+use crate::key::normalize_key;
+use crate::options::{ParserOptions, TrimPolicy};
+use crate::parser::{DuplicatePolicy, report_from_document};
+use crate::quote::{find_unescaped, is_quote, quote_value, unescape};
+
+/// 1行分の構文要素。改行コードは `Line` 側で保持する。
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LineKind {
+    /// 空行（空白のみの行も含む）
+    Blank(String),
+
+    /// コメント行（先頭の空白とコメント記号を含めてそのまま保持）
+    Comment(String),
+
+    /// `key = value` の行
+    Entry(EntryLine),
+
+    /// `-key` のみの行（systemd-sysctl でグロブの適用対象から除外する指定）
+    Exclusion(ExclusionLine),
+
+    /// `=` を含まない不正な行（内容はそのまま保持）
+    Invalid(String),
+}
+
+/// `-key` 行。`leading + "-" + key + trailing` で元の行に戻る。
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ExclusionLine {
+    pub leading: String,
+    /// `-` を除いたキー
+    pub key: String,
+    /// キーの後ろの空白と行末コメント
+    pub trailing: String,
+}
+
+/// `key = value` 行をトークンと周辺の空白（trivia）に分解したもの
+///
+/// `leading + ("-") + key + before_eq + "=" + after_eq + raw_value + trailing` で元の行に戻る。
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EntryLine {
+    pub leading: String,
+    /// 行頭の `-`（systemd-sysctl で書き込み失敗を無視する指定）
+    pub ignore_failure: bool,
+    /// `-` を除いたキー
+    pub key: String,
+    pub before_eq: String,
+    pub after_eq: String,
+    /// 引用符・エスケープを展開した値
+    pub value: String,
+    /// 書かれたままの値（引用符・エスケープを含む）
+    pub raw_value: String,
+    /// 値を囲んでいた引用符（`quoted_values` が有効な場合のみ）
+    pub quote: Option<char>,
+    /// 値の後ろの空白と行末コメント
+    pub trailing: String,
+}
+
+/// 改行コードを含む1行
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Line {
+    pub kind: LineKind,
+    /// `"\n"`, `"\r\n"`、または最終行で改行がない場合は `""`
+    pub newline: String,
+    /// 行末の `\` で複数の物理行をつないだ場合の原文（最後の改行を除く）
+    ///
+    /// `kind` はつないだ後の1行を表す。`kind` を編集した場合は `None` に戻して1行で書き出す。
+    pub raw: Option<String>,
+}
+
+impl Line {
+    /// この行が占める物理行の数
+    pub fn physical_lines(&self) -> usize {
+        self.raw.as_ref().map_or(1, |raw| raw.matches('\n').count() + 1)
+    }
+}
+
+/// 行末が継続を表す `\` かどうか（`\\` は継続にならない）
+pub(crate) fn ends_with_continuation(line: &str) -> bool {
+    let backslashes = line.len() - line.trim_end_matches('\\').len();
+    backslashes % 2 == 1
+}
+
+/// 継続行をつなぐ。`\` とその前後の空白は1つの空白になる。
+pub(crate) fn join_continuation(current: &mut String, next: &str) {
+    current.pop();
+    current.truncate(current.trim_end().len());
+    current.push(' ');
+    current.push_str(next.trim_start());
+}
+
+/// コメント・空行・順序・空白をすべて保持する sysctl.conf のドキュメント
+///
+/// `to_string()` で入力とバイト単位で同一の文字列に戻る。
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ConfDocument {
+    lines: Vec<Line>,
+    options: ParserOptions,
+}
+
+/// 文字列を先頭の空白・本体・末尾の空白の3つに分割する
+fn split_trivia(s: &str) -> (&str, &str, &str) {
+    let start = s.len() - s.trim_start().len();
+    let end = s.trim_end().len().max(start);
+    (&s[..start], &s[start..end], &s[end..])
+}
+
+/// `trim` が false のときは空白も本体に含める
+fn split_trivia_if(s: &str, trim: bool) -> (&str, &str, &str) {
+    if trim { split_trivia(s) } else { ("", s, "") }
+}
+
+/// `=` のない行を `-key` の除外指定として解釈する
+fn tokenize_exclusion(line: &str, options: &ParserOptions) -> Option<LineKind> {
+    let comment_pos = options.inline_comment_pos(line);
+    let (leading, body, ws) = split_trivia(&line[..comment_pos]);
+    let key = body.strip_prefix('-')?;
+    if key.is_empty() || key.contains(char::is_whitespace) {
+        return None;
+    }
+
+    Some(LineKind::Exclusion(ExclusionLine {
+        leading: leading.to_string(),
+        key: key.to_string(),
+        trailing: format!("{}{}", ws, &line[comment_pos..]),
+    }))
+}
+
+/// 改行コードを含まない1行をトークンに分解する
+pub(crate) fn tokenize_line(line: &str, options: &ParserOptions) -> LineKind {
+    match tokenize_entry(line, options) {
+        LineTokens::Entry(tokens) => LineKind::Entry(tokens.into_entry_line()),
+        LineTokens::Other(kind) => kind,
+    }
+}
+
+/// `tokenize_entry` の結果
+pub(crate) enum LineTokens<'a> {
+    Entry(EntryTokens<'a>),
+    /// エントリでない行（空行・コメント・除外指定・不正な行）
+    Other(LineKind),
+}
+
+/// `key = value` 行を入力から借用したまま分解したもの
+pub(crate) struct EntryTokens<'a> {
+    leading: &'a str,
+    pub(crate) ignore_failure: bool,
+    pub(crate) key: &'a str,
+    before_eq: &'a str,
+    pub(crate) value: ValueTokens<'a>,
+}
+
+impl EntryTokens<'_> {
+    fn into_entry_line(self) -> EntryLine {
+        EntryLine {
+            leading: self.leading.to_string(),
+            ignore_failure: self.ignore_failure,
+            key: self.key.to_string(),
+            before_eq: self.before_eq.to_string(),
+            after_eq: self.value.after_eq.to_string(),
+            value: self.value.value.into_owned(),
+            raw_value: self.value.raw.to_string(),
+            quote: self.value.quote,
+            trailing: self.value.trailing.to_string(),
+        }
+    }
+}
+
+/// 1行を `key = value` として分解する。エントリでない行は対応する `LineKind` を返す。
+pub(crate) fn tokenize_entry<'a>(line: &'a str, options: &ParserOptions) -> LineTokens<'a> {
+    if line.trim().is_empty() {
+        return LineTokens::Other(LineKind::Blank(line.to_string()));
+    }
+    if options.is_ignorable_line(line) {
+        return LineTokens::Other(LineKind::Comment(line.to_string()));
+    }
+
+    let Some((raw_key, raw_value)) = line.split_once('=') else {
+        return LineTokens::Other(tokenize_exclusion(line, options).unwrap_or_else(|| LineKind::Invalid(line.to_string())));
+    };
+
+    let (leading, key, before_eq) = split_trivia_if(raw_key, options.trim != TrimPolicy::None);
+
+    // `-key = value` は書き込み失敗を無視する代入（`-` の直後にキーが必要）
+    let (ignore_failure, key) = match key.strip_prefix('-') {
+        Some(rest) if rest.is_empty() || rest.starts_with(char::is_whitespace) => {
+            return LineTokens::Other(LineKind::Invalid(line.to_string()));
+        }
+        Some(rest) => (true, rest),
+        None => (false, key),
+    };
+
+    let Some(value) = tokenize_value(raw_value, options) else {
+        return LineTokens::Other(LineKind::Invalid(line.to_string()));
+    };
+
+    LineTokens::Entry(EntryTokens {
+        leading,
+        ignore_failure,
+        key,
+        before_eq,
+        value,
+    })
+}
+
+/// `=` より後ろを分解したもの
+pub(crate) struct ValueTokens<'a> {
+    after_eq: &'a str,
+    raw: &'a str,
+    pub(crate) value: Cow<'a, str>,
+    quote: Option<char>,
+    trailing: &'a str,
+}
+
+/// `=` より後ろを値と trivia に分解する。閉じていない引用符などは `None`。
+fn tokenize_value<'a>(raw_value: &'a str, options: &ParserOptions) -> Option<ValueTokens<'a>> {
+    let trim = options.trim == TrimPolicy::KeysAndValues;
+
+    if !options.quoted_values {
+        // 行末のコメント（#以降）は trailing 側に含める
+        let comment_pos = options.inline_comment_pos(raw_value);
+        let (after_eq, value, _) = split_trivia_if(&raw_value[..comment_pos], trim);
+        return Some(ValueTokens {
+            after_eq,
+            raw: value,
+            value: Cow::Borrowed(value),
+            quote: None,
+            trailing: &raw_value[after_eq.len() + value.len()..],
+        });
+    }
+
+    let body_start = raw_value.len() - raw_value.trim_start().len();
+    let body = &raw_value[body_start..];
+
+    if let Some(quote) = body.chars().next().filter(|&c| is_quote(c)) {
+        // 閉じ引用符の後ろには空白と行末コメントしか書けない
+        let end = find_unescaped(&body[1..], quote)? + 2;
+        let rest = &body[end..];
+        if !rest[..options.inline_comment_pos(rest)].trim().is_empty() {
+            return None;
+        }
+        return Some(ValueTokens {
+            after_eq: &raw_value[..body_start],
+            raw: &body[..end],
+            value: unescape(&body[1..end - 1]),
+            quote: Some(quote),
+            trailing: rest,
+        });
+    }
+
+    // 引用符なしの値では `\#` を除く最初の `#` から行末コメントになる
+    let comment_pos = if options.inline_comments {
+        find_unescaped(raw_value, '#').unwrap_or(raw_value.len())
+    } else {
+        raw_value.len()
+    };
+    let (after_eq, value, _) = split_trivia_if(&raw_value[..comment_pos], trim);
+    Some(ValueTokens {
+        after_eq,
+        raw: value,
+        value: unescape(value),
+        quote: None,
+        trailing: &raw_value[after_eq.len() + value.len()..],
+    })
+}
+
+/// 引用符なしでは書けない値かどうか
+fn needs_quoting(value: &str) -> bool {
+    value.trim() != value || value.contains(['#', '\\', '\n']) || value.starts_with(is_quote)
+}
+
+/// 値を書き込むときの表記。元の引用符を保ち、必要なら `"` で囲む。
+fn render_value(options: &ParserOptions, value: &str, quote: Option<char>) -> (String, Option<char>) {
+    let quote = quote.or_else(|| (options.quoted_values && needs_quoting(value)).then_some('"'));
+    match quote {
+        Some(q) => (quote_value(value, q), Some(q)),
+        None => (value.to_string(), None),
+    }
+}
+
+/// 書き込むと再パース結果が変わってしまうキー・値を拒否する
+fn check_value(
+    options: &ParserOptions,
+    line_number: usize,
+    key: &str,
+    value: &str,
+    quoted: bool,
+) -> Result<(), ParseError> {
+    let key_ok = !key.trim().is_empty() && key.trim() == key && !key.contains(['=', '#', '\n', '\r']);
+    let value_ok = !value.contains('\r') && (quoted || (value.trim() == value && !value.contains(['#', '\n'])));
+    if !key_ok || !value_ok || options.is_ignorable_line(key) || key.starts_with('-') {
+        return Err(ParseError::InvalidLine {
+            line_number,
+            content: format!("{} = {}", key, value),
+        });
+    }
+    if let Some(max) = options.max_value_length
+        && value.len() > max
+    {
+        return Err(ParseError::ValueTooLong {
+            line_number,
+            key: key.to_string(),
+            length: value.len(),
+            max,
+        });
+    }
+    Ok(())
+}
+
+impl EntryLine {
+    /// 正規形（ドット区切り）のキー。`net/ipv4/ip_forward` は `net.ipv4.ip_forward` になる。
+    pub fn canonical_key(&self) -> String {
+        normalize_key(&self.key)
+    }
+
+    /// 行内でのキーのバイト範囲
+    pub fn key_range(&self) -> Range<usize> {
+        let start = self.leading.len() + usize::from(self.ignore_failure);
+        start..start + self.key.len()
+    }
+
+    /// 行内での値のバイト範囲
+    pub fn value_range(&self) -> Range<usize> {
+        let start = self.key_range().end + self.before_eq.len() + 1 + self.after_eq.len();
+        start..start + self.raw_value.len()
+    }
+}
+
+impl fmt::Display for EntryLine {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}{}{}{}={}{}{}",
+            self.leading,
+            if self.ignore_failure { "-" } else { "" },
+            self.key,
+            self.before_eq,
+            self.after_eq,
+            self.raw_value,
+            self.trailing
+        )
+    }
+}
+
+impl fmt::Display for Line {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(raw) = &self.raw {
+            return write!(f, "{}{}", raw, self.newline);
+        }
+        match &self.kind {
+            LineKind::Blank(s) | LineKind::Comment(s) | LineKind::Invalid(s) => f.write_str(s)?,
+            LineKind::Entry(entry) => write!(f, "{}", entry)?,
+            LineKind::Exclusion(exclusion) => {
+                write!(f, "{}-{}{}", exclusion.leading, exclusion.key, exclusion.trailing)?
+            }
+        }
+        f.write_str(&self.newline)
+    }
+}
+
+impl fmt::Display for ConfDocument {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for line in &self.lines {
+            write!(f, "{}", line)?;
+        }
+        Ok(())
+    }
+}
+
+impl ConfDocument {
+    /// 文字列をロスレスにパースする。不正な行も `LineKind::Invalid` として保持する。
+    pub fn parse(input: &str) -> Self {
+        Self::parse_with(input, &ParserOptions::default())
+    }
+
+    /// オプションを指定してロスレスにパースする
+    pub fn parse_with(input: &str, options: &ParserOptions) -> Self {
+        let physical: Vec<(&str, &str)> = input
+            .split_inclusive('\n')
+            .map(|raw| {
+                if let Some(body) = raw.strip_suffix("\r\n") {
+                    (body, "\r\n")
+                } else if let Some(body) = raw.strip_suffix('\n') {
+                    (body, "\n")
+                } else {
+                    (raw, "")
+                }
+            })
+            .collect();
+
+        let mut lines = Vec::with_capacity(physical.len());
+        let mut i = 0;
+        while i < physical.len() {
+            let (body, mut newline) = physical[i];
+            i += 1;
+
+            // コメント行・空行は継続しない
+            if !options.line_continuation || options.is_ignorable_line(body) || !ends_with_continuation(body) {
+                lines.push(Line {
+                    kind: tokenize_line(body, options),
+                    newline: newline.to_string(),
+                    raw: None,
+                });
+                continue;
+            }
+
+            let mut raw = body.to_string();
+            let mut joined = body.to_string();
+            while ends_with_continuation(&joined) && i < physical.len() && !newline.is_empty() {
+                let (next, next_newline) = physical[i];
+                raw.push_str(newline);
+                raw.push_str(next);
+                join_continuation(&mut joined, next);
+                newline = next_newline;
+                i += 1;
+            }
+
+            lines.push(Line {
+                kind: tokenize_line(&joined, options),
+                newline: newline.to_string(),
+                raw: (raw != joined).then_some(raw),
+            });
+        }
+
+        ConfDocument {
+            lines,
+            options: options.clone(),
+        }
+    }
+
+    /// ファイルから読み込んでロスレスにパースする
+    pub fn from_file<P: AsRef<Path>>(path: P) -> Result<Self, ParseError> {
+        Self::from_file_with(path, &ParserOptions::default())
+    }
+
+    /// オプションを指定してファイルから読み込み、ロスレスにパースする
+    pub fn from_file_with<P: AsRef<Path>>(path: P, options: &ParserOptions) -> Result<Self, ParseError> {
+        let content = fs::read_to_string(&path)?;
+        Ok(Self::parse_with(&content, options))
+    }
+
+    /// パースに使ったオプション
+    pub fn options(&self) -> &ParserOptions {
+        &self.options
+    }
+
+    /// マップに格納するときのキー（`normalize_keys` が有効なら正規形）
+    pub fn entry_key(&self, entry: &EntryLine) -> String {
+        if self.options.normalize_keys {
+            entry.canonical_key()
+        } else {
+            entry.key.clone()
+        }
+    }
+
+    fn lookup_key(&self, key: &str) -> String {
+        if self.options.normalize_keys {
+            normalize_key(key)
+        } else {
+            key.to_string()
+        }
+    }
+
+    /// すべての行
+    pub fn lines(&self) -> &[Line] {
+        &self.lines
+    }
+
+    /// 各行を (開始位置の物理行番号, 行) の形で返す
+    pub fn numbered_lines(&self) -> impl Iterator<Item = (usize, &Line)> {
+        self.lines.iter().scan(1, |next, line| {
+            let line_number = *next;
+            *next += line.physical_lines();
+            Some((line_number, line))
+        })
+    }
+
+    /// `key = value` 行を (行番号, 行) の形で出現順に返す
+    pub fn entries(&self) -> impl Iterator<Item = (usize, &EntryLine)> {
+        self.numbered_lines().filter_map(|(line_number, line)| match &line.kind {
+            LineKind::Entry(entry) => Some((line_number, entry)),
+            _ => None,
+        })
+    }
+
+    /// キーに対応する値（ドット区切り・スラッシュ区切りは同一視）
+    ///
+    /// 重複時はオプションの `duplicate_policy` に従う（`FirstWins` なら最初、それ以外は最後の値）。
+    pub fn get(&self, key: &str) -> Option<&str> {
+        let idx = self.effective_index_of(key)?;
+        match &self.lines[idx].kind {
+            LineKind::Entry(entry) => Some(entry.value.as_str()),
+            _ => unreachable!("effective_index_of only returns entry lines"),
+        }
+    }
+
+    /// 値を更新する。キーがあれば `get` が返す値の行だけを書き換え、なければ末尾に追加する。
+    pub fn set(&mut self, key: &str, value: &str) -> Result<(), ParseError> {
+        match self.effective_index_of(key) {
+            Some(idx) => {
+                let LineKind::Entry(entry) = &self.lines[idx].kind else {
+                    unreachable!("effective_index_of only returns entry lines");
+                };
+                let (raw_value, quote) = render_value(&self.options, value, entry.quote);
+                check_value(&self.options, self.line_number_at(idx), key, value, quote.is_some())?;
+                self.lines[idx].raw = None;
+                if let LineKind::Entry(entry) = &mut self.lines[idx].kind {
+                    entry.value = value.to_string();
+                    entry.raw_value = raw_value;
+                    entry.quote = quote;
+                    // 空の値に値を入れる場合は `=` の後ろに空白を補う
+                    if entry.after_eq.is_empty() && !entry.before_eq.is_empty() {
+                        entry.after_eq = " ".to_string();
+                    }
+                }
+            }
+            None => {
+                let idx = self.lines.len();
+                self.insert_line(idx, key, value)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// キーの行をすべて削除する。削除した場合は true を返す。
+    pub fn remove(&mut self, key: &str) -> bool {
+        let targets = self.indices_of(key);
+        for &idx in targets.iter().rev() {
+            self.lines.remove(idx);
+        }
+        !targets.is_empty()
+    }
+
+    /// `anchor` の最後の出現行の直後に新しい行を挿入する。`anchor` がなければ false を返す。
+    pub fn insert_after(&mut self, anchor: &str, key: &str, value: &str) -> Result<bool, ParseError> {
+        let Some(idx) = self.last_index_of(anchor) else {
+            return Ok(false);
+        };
+        self.insert_line(idx + 1, key, value)?;
+        Ok(true)
+    }
+
+    /// キーの行をすべて `# ` でコメントアウトする。対象があれば true を返す。
+    pub fn comment_out(&mut self, key: &str) -> bool {
+        let targets = self.indices_of(key);
+        for &idx in &targets {
+            if let LineKind::Entry(entry) = &self.lines[idx].kind {
+                self.lines[idx].kind =
+                    LineKind::Comment(format!("{}# {}", entry.leading, entry.to_string().trim_start()));
+                self.lines[idx].raw = None;
+            }
+        }
+        !targets.is_empty()
+    }
+
+    /// ドキュメントをファイルに書き戻す
+    ///
+    /// 同じディレクトリの一時ファイルに書いてから置き換えるので、途中で失敗しても元のファイルは壊れない。
+    /// 既存のファイルのパーミッションを引き継ぎ、シンボリックリンクはリンク先のファイルを置き換える。
+    pub fn write_to_file<P: AsRef<Path>>(&self, path: P) -> Result<(), ParseError> {
+        let path = fs::canonicalize(&path).unwrap_or_else(|_| path.as_ref().to_path_buf());
+        let Some(file_name) = path.file_name() else {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, format!("not a file path: {}", path.display())).into());
+        };
+        let mut tmp_name = OsString::from(".");
+        tmp_name.push(file_name);
+        tmp_name.push(format!(".{}.tmp", std::process::id()));
+        let tmp = path.with_file_name(tmp_name);
+
+        let permissions = fs::metadata(&path).ok().map(|metadata| metadata.permissions());
+        let result = write_synced(&tmp, self.to_string().as_bytes(), permissions).and_then(|()| fs::rename(&tmp, &path));
+        if result.is_err() {
+            let _ = fs::remove_file(&tmp);
+        }
+        Ok(result?)
+    }
+
+    /// キーに一致する行の位置（昇順）
+    fn indices_of(&self, key: &str) -> Vec<usize> {
+        let key = self.lookup_key(key);
+        self.lines
+            .iter()
+            .enumerate()
+            .filter(|(_, line)| matches!(&line.kind, LineKind::Entry(entry) if self.entry_key(entry) == key))
+            .map(|(i, _)| i)
+            .collect()
+    }
+
+    /// `lines[idx]` が始まる物理行の行番号（`numbered_lines` と同じ数え方）
+    fn line_number_at(&self, idx: usize) -> usize {
+        1 + self.lines[..idx].iter().map(Line::physical_lines).sum::<usize>()
+    }
+
+    fn last_index_of(&self, key: &str) -> Option<usize> {
+        self.indices_of(key).pop()
+    }
+
+    /// `duplicate_policy` で採用される行の位置
+    fn effective_index_of(&self, key: &str) -> Option<usize> {
+        let indices = self.indices_of(key);
+        match self.options.duplicate_policy {
+            DuplicatePolicy::FirstWins => indices.first().copied(),
+            _ => indices.last().copied(),
+        }
+    }
+
+    /// 既存の改行コードに合わせて `key = value` 行を挿入する
+    fn insert_line(&mut self, idx: usize, key: &str, value: &str) -> Result<(), ParseError> {
+        let (raw_value, quote) = render_value(&self.options, value, None);
+        check_value(&self.options, self.line_number_at(idx), key, value, quote.is_some())?;
+
+        let newline = self
+            .lines
+            .iter()
+            .map(|line| line.newline.as_str())
+            .find(|nl| !nl.is_empty())
+            .unwrap_or("\n")
+            .to_string();
+
+        // 改行のない最終行の後ろに追加する場合は改行を補う
+        if idx > 0 && self.lines[idx - 1].newline.is_empty() {
+            self.lines[idx - 1].newline = newline.clone();
+        }
+
+        self.lines.insert(idx, Line {
+            kind: LineKind::Entry(EntryLine {
+                leading: String::new(),
+                ignore_failure: false,
+                key: key.to_string(),
+                before_eq: " ".to_string(),
+                after_eq: if value.is_empty() { String::new() } else { " ".to_string() },
+                value: value.to_string(),
+                raw_value,
+                quote,
+                trailing: String::new(),
+            }),
+            newline,
+            raw: None,
+        });
+        Ok(())
+    }
+
+    /// `key = value` 行を検証しながら出現順に返す。
+    ///
+    /// 不正な行・長すぎる値・（許可していない場合の）空の値は `Err` になる。
+    /// `max_lines` を超えた場合は `ParseError::TooManyLines` を1つ返して終わる。
+    pub fn checked_entries(&self) -> impl Iterator<Item = Result<(usize, &EntryLine), ParseError>> {
+        let options = &self.options;
+        let mut exceeded = false;
+
+        self.numbered_lines().filter_map(move |(line_number, line)| {
+            if exceeded {
+                return None;
+            }
+            if let Some(max) = options.max_lines
+                && line_number + line.physical_lines() - 1 > max
+            {
+                exceeded = true;
+                return Some(Err(ParseError::TooManyLines {
+                    line_number: max + 1,
+                    limit: max,
+                }));
+            }
+            match &line.kind {
+                LineKind::Invalid(content) => Some(Err(ParseError::InvalidLine {
+                    line_number,
+                    content: content.clone(),
+                })),
+                LineKind::Entry(entry) => {
+                    Some(check_entry(options, line_number, entry).map(|()| (line_number, entry)))
+                }
+                LineKind::Blank(_) | LineKind::Comment(_) | LineKind::Exclusion(_) => None,
+            }
+        })
+    }
+
+    /// `parse_str_with` と同じ規則で BTreeMap に変換する（重複キーはオプションの `duplicate_policy` に従う）
+    pub fn to_map(&self) -> Result<BTreeMap<String, String>, ParseError> {
+        Ok(report_from_document(self)?.values)
+    }
+
+    /// キーが最初に現れた順序を保った `Config` に変換する（値は `duplicate_policy` に従う）
+    pub fn to_config(&self) -> Result<Config, ParseError> {
+        let mut values = report_from_document(self)?.values;
+
+        Ok(self
+            .entries()
+            .filter_map(|(_, entry)| values.remove_entry(&self.entry_key(entry)))
+            .collect())
+    }
+}
+
+/// パース済みの1エントリをオプションの制限に照らして検証する
+pub(crate) fn check_entry(options: &ParserOptions, line_number: usize, entry: &EntryLine) -> Result<(), ParseError> {
+    check_entry_value(options, line_number, &entry.key, &entry.value)
+}
+
+/// キーと値をオプションの制限に照らして検証する
+pub(crate) fn check_entry_value(
+    options: &ParserOptions,
+    line_number: usize,
+    key: &str,
+    value: &str,
+) -> Result<(), ParseError> {
+    if let Some(max) = options.max_value_length
+        && value.len() > max
+    {
+        return Err(ParseError::ValueTooLong {
+            line_number,
+            key: key.to_string(),
+            length: value.len(),
+            max,
+        });
+    }
+    if !options.allow_empty_values && value.trim().is_empty() {
+        return Err(ParseError::EmptyValue {
+            line_number,
+            key: key.to_string(),
+        });
+    }
+    Ok(())
+}
+
+/// ファイルを作って書き込み、ディスクに書き出されるまで待つ
+fn write_synced(path: &Path, content: &[u8], permissions: Option<fs::Permissions>) -> io::Result<()> {
+    let mut file = fs::File::create(path)?;
+    file.write_all(content)?;
+    if let Some(permissions) = permissions {
+        file.set_permissions(permissions)?;
+    }
+    file.sync_all()
+}
@@ -12,11 +12,58 @@ pub enum ParseError {
         content: String,
     },
 
-    /// 値が4096文字を超えている（Linuxの仕様準拠）
+    /// 値が最大長（デフォルトは4096文字、Linuxの仕様準拠）を超えている
     ValueTooLong {
         line_number: usize,
         key: String,
         length: usize,
+        max: usize,
+    },
+
+    /// 空の値が許可されていない
+    EmptyValue {
+        line_number: usize,
+        key: String,
+    },
+
+    /// 入力の行数が上限を超えている
+    TooManyLines {
+        line_number: usize,
+        limit: usize,
+    },
+
+    /// 重複キーをエラーにする設定で、同じキーが2回以上代入された
+    DuplicateKey {
+        key: String,
+        first_line: usize,
+        line_number: usize,
+    },
+
+    /// 型付きの取得（`Config::get`）で、キーが存在しない
+    MissingKey {
+        key: String,
+    },
+
+    /// 型付きの取得（`Config::get`）で、値を指定した型に変換できない
+    InvalidValue {
+        key: String,
+        value: String,
+        reason: String,
+    },
+
+    /// JSON への変換で、値を持つキーの下に別のキーがネストしている（`log = on` と `log.file = x`）
+    KeyConflict {
+        key: String,
+        conflicting_key: String,
+    },
+
+    /// serde によるデシリアライズ・シリアライズのエラー（フィールドの不足や書き出せない構造など）
+    Serde(String),
+
+    /// 複数ファイルの読み込み時に、どのファイルでエラーが起きたか
+    InFile {
+        path: String,
+        error: Box<ParseError>,
     },
 }
 
@@ -33,15 +80,53 @@ impl fmt::Display for ParseError {
             ParseError::InvalidLine { line_number, content } => {
                 write!(f, "Invalid line at {}: '{}'", line_number, content)
             }
-            ParseError::ValueTooLong { line_number, key, length } => {
+            ParseError::ValueTooLong { line_number, key, length, max } => {
+                write!(
+                    f,
+                    "Value too long at line {}: key '{}' has {} characters (max {})",
+                    line_number, key, length, max
+                )
+            }
+            ParseError::EmptyValue { line_number, key } => {
+                write!(f, "Empty value at line {}: key '{}'", line_number, key)
+            }
+            ParseError::TooManyLines { line_number, limit } => {
+                write!(f, "Too many lines at line {}: input exceeds {} lines", line_number, limit)
+            }
+            ParseError::DuplicateKey { key, first_line, line_number } => {
+                write!(
+                    f,
+                    "Duplicate key at line {}: '{}' is already set at line {}",
+                    line_number, key, first_line
+                )
+            }
+            ParseError::MissingKey { key } => write!(f, "Missing key: '{}'", key),
+            ParseError::InvalidValue { key, value, reason } => {
+                write!(f, "Invalid value for key '{}': '{}' ({})", key, value, reason)
+            }
+            ParseError::KeyConflict { key, conflicting_key } => {
                 write!(
                     f,
-                    "Value too long at line {}: key '{}' has {} characters (max 4096)",
-                    line_number, key, length
+                    "Key conflict: '{}' and '{}' cannot both be converted to nested form",
+                    key, conflicting_key
                 )
             }
+            ParseError::Serde(msg) => write!(f, "Serde error: {}", msg),
+            ParseError::InFile { path, error } => write!(f, "{}: {}", path, error),
         }
     }
 }
 
 impl std::error::Error for ParseError {}
+
+impl serde::de::Error for ParseError {
+    fn custom<T: fmt::Display>(msg: T) -> Self {
+        ParseError::Serde(msg.to_string())
+    }
+}
+
+impl serde::ser::Error for ParseError {
+    fn custom<T: fmt::Display>(msg: T) -> Self {
+        ParseError::Serde(msg.to_string())
+    }
+}
@@ -0,0 +1,250 @@
+use std::fmt::Write as _;
+use serde_json::Value;
+use crate::ParseError;
+use crate::key::SysctlKey;
+use crate::quote::{is_quote, quote_value};
+
+/// ネストされた JSON（`flatten_to_nested_json` や `flatten_to_typed_json` の結果）を YAML にする
+pub fn to_yaml(value: &Value) -> Result<String, ParseError> {
+    serde_yaml::to_string(value).map_err(|e| ParseError::Serde(e.to_string()))
+}
+
+/// ネストされた JSON を TOML にする。ネストしたオブジェクトは `[net.ipv4]` のようなドット区切りのテーブルになる。
+///
+/// TOML で表せない `null` は書き出さない。
+pub fn to_toml(value: &Value) -> Result<String, ParseError> {
+    toml::to_string(&without_nulls(value)).map_err(|e| ParseError::Serde(e.to_string()))
+}
+
+/// ネストされた JSON を INI にする
+///
+/// キーの最初のセグメントをセクション名、残りをドット区切りのキーにする（`[net]` の下に `ipv4.ip_forward = 1`）。
+/// セグメントが1つしかないキーは最初のセクションより前に書く。配列は空白区切りの1つの値になる。
+/// セグメント内の `.` は `SysctlKey` と同じく `/` で表す（`conf.eth0/100.rp_filter`）。`.` を含むセクション名と
+/// `/` を含むセグメントはエラー。`;` や `#` を含む値はコメントと読まれないように `"` で囲む。
+pub fn to_ini(value: &Value) -> Result<String, ParseError> {
+    let mut global = String::new();
+    let mut sections = String::new();
+
+    for (section, child) in top_level(value)? {
+        match child {
+            Value::Object(_) => {
+                if section.contains('.') {
+                    return Err(unwritable_segment(std::slice::from_ref(section), section));
+                }
+                let mut leaves = Vec::new();
+                collect_leaves(child, &mut vec![section.clone()], &mut leaves)?;
+                let _ = writeln!(sections, "\n[{}]", section);
+                for (path, text) in leaves {
+                    let _ = writeln!(sections, "{} = {}", ini_key(&path, &path[1..])?, ini_value(&text));
+                }
+            }
+            scalar => {
+                if let Some(text) = leaf_text(section, scalar)? {
+                    let path = std::slice::from_ref(section);
+                    let _ = writeln!(global, "{} = {}", ini_key(path, path)?, ini_value(&text));
+                }
+            }
+        }
+    }
+
+    if global.is_empty() {
+        Ok(sections.trim_start_matches('\n').to_string())
+    } else {
+        Ok(global + &sections)
+    }
+}
+
+/// 環境変数ファイルに書き出すときのキーの変換規則
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EnvOptions {
+    pub(crate) prefix: String,
+    pub(crate) separator: String,
+    pub(crate) uppercase: bool,
+}
+
+impl Default for EnvOptions {
+    /// `net.ipv4.ip_forward` → `NET__IPV4__IP_FORWARD`（`from_env_vars` で元のキーに戻る）
+    fn default() -> Self {
+        EnvOptions {
+            prefix: String::new(),
+            separator: "__".to_string(),
+            uppercase: true,
+        }
+    }
+}
+
+impl EnvOptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// キーの先頭に付ける文字列（例: `SYSCTL_`）
+    pub fn prefix(mut self, prefix: impl Into<String>) -> Self {
+        self.prefix = prefix.into();
+        self
+    }
+
+    /// セグメントの区切り（デフォルト: `__`。`_` にするとセグメント内の `_` と区別できない）
+    pub fn separator(mut self, separator: impl Into<String>) -> Self {
+        self.separator = separator.into();
+        self
+    }
+
+    /// キーを大文字にするか（デフォルト: true）
+    pub fn uppercase(mut self, enabled: bool) -> Self {
+        self.uppercase = enabled;
+        self
+    }
+
+    /// セグメントの列を環境変数名にする。英数字と `_` 以外の文字は `_` になる。
+    pub(crate) fn env_key(&self, segments: &[String]) -> String {
+        let joined = segments
+            .iter()
+            .map(|segment| {
+                segment
+                    .chars()
+                    .map(|c| if c.is_ascii_alphanumeric() || c == '_' { c } else { '_' })
+                    .collect::<String>()
+            })
+            .collect::<Vec<_>>()
+            .join(&self.separator);
+        let key = format!("{}{}", self.prefix, joined);
+        if self.uppercase { key.to_ascii_uppercase() } else { key }
+    }
+}
+
+/// ネストされた JSON をシェルの `KEY=value` 形式の環境変数ファイルにする
+///
+/// 値は必要な場合だけシングルクォートで囲む。
+/// 環境変数名では `.` を区切りと区別できないため、`.` を含むセグメント（`eth0.100` など）はエラー。
+pub fn to_env(value: &Value, options: &EnvOptions) -> Result<String, ParseError> {
+    let mut leaves = Vec::new();
+    top_level(value)?;
+    collect_leaves(value, &mut Vec::new(), &mut leaves)?;
+
+    let mut out = String::new();
+    for (path, text) in leaves {
+        if let Some(segment) = path.iter().find(|segment| segment.contains('.')) {
+            return Err(unwritable_segment(&path, segment));
+        }
+        let _ = writeln!(out, "{}={}", options.env_key(&path), shell_quote(&text));
+    }
+    Ok(out)
+}
+
+fn top_level(value: &Value) -> Result<&serde_json::Map<String, Value>, ParseError> {
+    value.as_object().ok_or_else(|| ParseError::InvalidValue {
+        key: String::new(),
+        value: value.to_string(),
+        reason: "expected a JSON object at the top level".to_string(),
+    })
+}
+
+/// オブジェクトをたどり、(セグメントの列, 値の表記) を出現順に集める
+fn collect_leaves(value: &Value, path: &mut Vec<String>, out: &mut Vec<(Vec<String>, String)>) -> Result<(), ParseError> {
+    match value {
+        Value::Object(children) => {
+            for (key, child) in children {
+                path.push(key.clone());
+                collect_leaves(child, path, out)?;
+                path.pop();
+            }
+        }
+        leaf => {
+            if let Some(text) = leaf_text(&SysctlKey::from_segments(path.iter()).to_dotted(), leaf)? {
+                out.push((path.clone(), text));
+            }
+        }
+    }
+    Ok(())
+}
+
+/// INI のキーの表記（`segments` は `path` のうちキーに書く部分）
+fn ini_key(path: &[String], segments: &[String]) -> Result<String, ParseError> {
+    if let Some(segment) = segments.iter().find(|segment| segment.contains('/')) {
+        return Err(unwritable_segment(path, segment));
+    }
+    Ok(SysctlKey::from_segments(segments.iter()).to_dotted())
+}
+
+/// INI の値の表記。コメントと読まれる文字や前後の空白、先頭の引用符を含む値は `"` で囲む。
+fn ini_value(text: &str) -> String {
+    if text.contains([';', '#']) || text.trim() != text || text.starts_with(is_quote) {
+        quote_value(text, '"')
+    } else {
+        text.to_string()
+    }
+}
+
+fn unwritable_segment(path: &[String], segment: &str) -> ParseError {
+    ParseError::InvalidValue {
+        key: SysctlKey::from_segments(path.iter()).to_dotted(),
+        value: segment.to_string(),
+        reason: "the key segment cannot be written without changing the key".to_string(),
+    }
+}
+
+/// 値の表記。`null` は `None`、配列は空白区切り、1行に書けない値はエラー。
+fn leaf_text(key: &str, value: &Value) -> Result<Option<String>, ParseError> {
+    let text = match value {
+        Value::Null => return Ok(None),
+        Value::String(s) => s.clone(),
+        Value::Number(n) => n.to_string(),
+        Value::Bool(b) => b.to_string(),
+        Value::Array(items) => {
+            let mut parts = Vec::with_capacity(items.len());
+            for item in items {
+                match item {
+                    Value::Array(_) | Value::Object(_) => {
+                        return Err(ParseError::InvalidValue {
+                            key: key.to_string(),
+                            value: value.to_string(),
+                            reason: "nested arrays and objects cannot be written as one line".to_string(),
+                        });
+                    }
+                    item => parts.extend(leaf_text(key, item)?),
+                }
+            }
+            parts.join(" ")
+        }
+        Value::Object(_) => unreachable!("objects are expanded by the caller"),
+    };
+
+    if text.contains(['\n', '\r']) {
+        return Err(ParseError::InvalidValue {
+            key: key.to_string(),
+            value: text,
+            reason: "line breaks cannot be written as one line".to_string(),
+        });
+    }
+    Ok(Some(text))
+}
+
+/// シェルでそのまま読める形にする（安全な文字だけならそのまま、それ以外はシングルクォート）
+fn shell_quote(value: &str) -> String {
+    let safe = !value.is_empty()
+        && value
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || "_-.,:/@%+=".contains(c));
+    if safe {
+        value.to_string()
+    } else {
+        format!("'{}'", value.replace('\'', r"'\''"))
+    }
+}
+
+/// `null` を取り除いたコピー（TOML は `null` を表せない）
+fn without_nulls(value: &Value) -> Value {
+    match value {
+        Value::Object(children) => Value::Object(
+            children
+                .iter()
+                .filter(|(_, child)| !child.is_null())
+                .map(|(key, child)| (key.clone(), without_nulls(child)))
+                .collect(),
+        ),
+        Value::Array(items) => Value::Array(items.iter().filter(|item| !item.is_null()).map(without_nulls).collect()),
+        other => other.clone(),
+    }
+}
@@ -0,0 +1,179 @@
+use std::collections::{BTreeMap, BTreeSet};
+use crate::ParseError;
+use crate::document::{ConfDocument, LineKind};
+use crate::key::{SysctlKey, normalize_key};
+
+/// `net.ipv4.conf.*.rp_filter` のようなグロブを含むキー
+///
+/// `*`・`?`・`[...]` はセグメント（`SysctlKey` のパス要素）の中だけでマッチし、区切りをまたがない。
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct KeyPattern {
+    raw: String,
+    segments: Vec<String>,
+}
+
+impl KeyPattern {
+    pub fn new(pattern: &str) -> Self {
+        KeyPattern {
+            raw: pattern.to_string(),
+            segments: SysctlKey::parse(pattern).segments().to_vec(),
+        }
+    }
+
+    /// キーにグロブ文字が含まれているかどうか
+    pub fn is_glob(key: &str) -> bool {
+        key.contains(['*', '?', '['])
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.raw
+    }
+
+    /// 具体的なキーにマッチするかどうか
+    pub fn matches(&self, key: &str) -> bool {
+        let key = SysctlKey::parse(key);
+        key.segments().len() == self.segments.len()
+            && self
+                .segments
+                .iter()
+                .zip(key.segments())
+                .all(|(pattern, part)| wildcard_match(pattern.as_bytes(), part.as_bytes()))
+    }
+}
+
+/// fnmatch 相当のワイルドカードマッチ（`*`, `?`, `[abc]`, `[a-z]`, `[!x]`）
+fn wildcard_match(pattern: &[u8], text: &[u8]) -> bool {
+    match pattern.split_first() {
+        None => text.is_empty(),
+        Some((b'*', rest)) => (0..=text.len()).any(|i| wildcard_match(rest, &text[i..])),
+        Some((b'?', rest)) => !text.is_empty() && wildcard_match(rest, &text[1..]),
+        Some((b'[', rest)) => match (text.split_first(), match_class(rest)) {
+            (Some((&c, text_rest)), Some((class, negated, pattern_rest))) => {
+                class_contains(class, c) != negated && wildcard_match(pattern_rest, text_rest)
+            }
+            // 閉じ括弧のない `[` は通常の文字として扱う
+            (Some((&c, text_rest)), None) => c == b'[' && wildcard_match(rest, text_rest),
+            (None, _) => false,
+        },
+        Some((&p, rest)) => text.first() == Some(&p) && wildcard_match(rest, &text[1..]),
+    }
+}
+
+/// `[` の直後から文字クラスを読み取り、(クラス本体, 否定かどうか, 残りのパターン) を返す
+fn match_class(pattern: &[u8]) -> Option<(&[u8], bool, &[u8])> {
+    let (negated, body) = match pattern.first() {
+        Some(b'!') | Some(b'^') => (true, &pattern[1..]),
+        _ => (false, pattern),
+    };
+    // 先頭の `]` はクラスの文字として扱う
+    let end = body.iter().skip(1).position(|&c| c == b']')? + 1;
+    Some((&body[..end], negated, &body[end + 1..]))
+}
+
+fn class_contains(class: &[u8], c: u8) -> bool {
+    let mut i = 0;
+    while i < class.len() {
+        if i + 2 < class.len() && class[i + 1] == b'-' {
+            if class[i] <= c && c <= class[i + 2] {
+                return true;
+            }
+            i += 3;
+        } else {
+            if class[i] == c {
+                return true;
+            }
+            i += 1;
+        }
+    }
+    false
+}
+
+/// systemd-sysctl と同じ規則でグロブを展開するための設定
+///
+/// - グロブを含まないキーの代入は常にそのまま適用される
+/// - グロブはマッチしたキーのうち、明示的な代入も `-key` の除外もないものにだけ適用される
+/// - 同じキーに複数のグロブがマッチした場合は後に書かれた方が優先される
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SysctlRules {
+    explicit: BTreeMap<String, String>,
+    globs: Vec<(KeyPattern, String)>,
+    excluded: BTreeSet<String>,
+}
+
+impl SysctlRules {
+    /// 文字列をパースしてルールを作る
+    pub fn parse_str(input: &str) -> Result<Self, ParseError> {
+        Self::from_document(&ConfDocument::parse(input))
+    }
+
+    /// ドキュメントからルールを作る
+    pub fn from_document(doc: &ConfDocument) -> Result<Self, ParseError> {
+        let mut rules = SysctlRules::default();
+
+        for entry in doc.checked_entries() {
+            let (_, entry) = entry?;
+            rules.add(&entry.key, &entry.value);
+        }
+
+        for line in doc.lines() {
+            if let LineKind::Exclusion(exclusion) = &line.kind {
+                rules.exclude(&exclusion.key);
+            }
+        }
+
+        Ok(rules)
+    }
+
+    /// 代入を1つ追加する（後から追加したものが優先される）
+    pub fn add(&mut self, key: &str, value: &str) {
+        if KeyPattern::is_glob(key) {
+            self.globs.push((KeyPattern::new(key), value.to_string()));
+        } else {
+            self.explicit.insert(normalize_key(key), value.to_string());
+        }
+    }
+
+    /// `-key` の除外指定を追加する
+    pub fn exclude(&mut self, key: &str) {
+        self.excluded.insert(normalize_key(key));
+    }
+
+    /// グロブを含まない代入
+    pub fn explicit(&self) -> &BTreeMap<String, String> {
+        &self.explicit
+    }
+
+    /// グロブの代入（書かれた順）
+    pub fn globs(&self) -> &[(KeyPattern, String)] {
+        &self.globs
+    }
+
+    /// `-key` で除外されたキー
+    pub fn excluded(&self) -> &BTreeSet<String> {
+        &self.excluded
+    }
+
+    /// 実在するキーの一覧（procfs のスナップショットなど）に対してグロブを展開する
+    ///
+    /// 一覧のキーはスラッシュ区切りでもよい。結果のキーは正規形（ドット区切り）になる。
+    /// 明示的に代入されたキーは一覧に含まれていなくても結果に含まれる。
+    pub fn expand<I, S>(&self, concrete_keys: I) -> BTreeMap<String, String>
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        let mut result = self.explicit.clone();
+
+        for key in concrete_keys {
+            let key = normalize_key(key.as_ref());
+            if self.explicit.contains_key(&key) || self.excluded.contains(&key) {
+                continue;
+            }
+            if let Some((_, value)) = self.globs.iter().rev().find(|(pattern, _)| pattern.matches(&key)) {
+                result.insert(key, value.clone());
+            }
+        }
+
+        result
+    }
+}
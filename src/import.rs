@@ -0,0 +1,104 @@
+use std::collections::BTreeMap;
+use serde_json::{Number, Value};
+use crate::ParseError;
+use crate::converter::nested_json_to_flat;
+use crate::export::EnvOptions;
+use crate::key::SysctlKey;
+
+/// JSON をパースし、`parse_str` と同じフラットなマップにする
+///
+/// 変換の規則は `nested_json_to_flat` と同じ（配列は空白区切り、真偽値は `true` / `false`、`null` は読み飛ばす）。
+/// 真偽値はスキーマの `bool` と同じ表記なので、そのまま `validate_with_schema` に渡せる。
+pub fn from_json(input: &str) -> Result<BTreeMap<String, String>, ParseError> {
+    let value: Value = serde_json::from_str(input).map_err(|e| ParseError::Serde(e.to_string()))?;
+    nested_json_to_flat(&value)
+}
+
+/// YAML をパースし、`parse_str` と同じフラットなマップにする
+pub fn from_yaml(input: &str) -> Result<BTreeMap<String, String>, ParseError> {
+    let value: Value = serde_yaml::from_str(input).map_err(|e| ParseError::Serde(e.to_string()))?;
+    nested_json_to_flat(&value)
+}
+
+/// TOML をパースし、`parse_str` と同じフラットなマップにする（日時は TOML の表記のまま文字列になる）
+pub fn from_toml(input: &str) -> Result<BTreeMap<String, String>, ParseError> {
+    let value: toml::Table = input.parse().map_err(|e: toml::de::Error| ParseError::Serde(e.to_string()))?;
+    nested_json_to_flat(&toml_to_json(toml::Value::Table(value), &mut Vec::new())?)
+}
+
+/// 現在のプロセスの環境変数のうち、`prefix` で始まるものをフラットなマップにする
+///
+/// `SYSCTL_NET__IPV4__IP_FORWARD=1` は `net.ipv4.ip_forward = 1` になる（`__` でセグメントを区切り、小文字にする）。
+/// UTF-8 でない名前や値の変数は無視する。
+pub fn from_env_prefix(prefix: &str) -> BTreeMap<String, String> {
+    let vars = std::env::vars_os().filter_map(|(name, value)| Some((name.into_string().ok()?, value.into_string().ok()?)));
+    from_env_vars(vars, &EnvOptions::new().prefix(prefix))
+}
+
+/// (名前, 値) の列から、`EnvOptions` の規則で環境変数をフラットなマップにする
+///
+/// プレフィックスで始まらない変数と、プレフィックスだけの変数は無視する。
+/// `uppercase` が有効なら名前を小文字に戻す。値はそのまま使う。
+pub fn from_env_vars<I, K, V>(vars: I, options: &EnvOptions) -> BTreeMap<String, String>
+where
+    I: IntoIterator<Item = (K, V)>,
+    K: AsRef<str>,
+    V: Into<String>,
+{
+    let mut flat = BTreeMap::new();
+    for (name, value) in vars {
+        let Some(rest) = name.as_ref().strip_prefix(options.prefix.as_str()) else {
+            continue;
+        };
+        if rest.is_empty() {
+            continue;
+        }
+        let rest = if options.uppercase { rest.to_ascii_lowercase() } else { rest.to_string() };
+        let segments = if options.separator.is_empty() {
+            vec![rest]
+        } else {
+            rest.split(options.separator.as_str()).map(str::to_string).collect()
+        };
+        flat.insert(SysctlKey::from_segments(segments).to_dotted(), value.into());
+    }
+    flat
+}
+
+/// TOML の値を JSON にする。JSON で表せない `nan` / `inf` は `path` のキーの `InvalidValue` になる。
+fn toml_to_json(value: toml::Value, path: &mut Vec<String>) -> Result<Value, ParseError> {
+    let json = match value {
+        toml::Value::String(s) => Value::String(s),
+        toml::Value::Integer(n) => Value::from(n),
+        toml::Value::Float(n) => match Number::from_f64(n) {
+            Some(n) => Value::Number(n),
+            None => {
+                return Err(ParseError::InvalidValue {
+                    key: SysctlKey::from_segments(path.iter()).to_dotted(),
+                    value: n.to_string(),
+                    reason: "non-finite floats cannot be converted".to_string(),
+                });
+            }
+        },
+        toml::Value::Boolean(b) => Value::Bool(b),
+        toml::Value::Datetime(dt) => Value::String(dt.to_string()),
+        toml::Value::Array(items) => {
+            let mut array = Vec::with_capacity(items.len());
+            for (i, item) in items.into_iter().enumerate() {
+                path.push(i.to_string());
+                array.push(toml_to_json(item, path)?);
+                path.pop();
+            }
+            Value::Array(array)
+        }
+        toml::Value::Table(table) => {
+            let mut object = serde_json::Map::with_capacity(table.len());
+            for (key, item) in table {
+                path.push(key.clone());
+                object.insert(key, toml_to_json(item, path)?);
+                path.pop();
+            }
+            Value::Object(object)
+        }
+    };
+    Ok(json)
+}
@@ -0,0 +1,89 @@
+use std::fmt;
+use std::str::FromStr;
+
+/// sysctl のキーをセグメント（パスの各要素）の列として表したもの
+///
+/// sysctl(8) や systemd と同じく、ドット区切り（`net.ipv4.ip_forward`）と
+/// スラッシュ区切り（`net/ipv4/ip_forward`）の両方を受け付ける。
+/// 最初に現れた区切り文字が `/` ならスラッシュ区切りとみなし、`.` はセグメントの一部になる
+/// （例: `net/ipv4/conf/eth0.100/rp_filter` の `eth0.100`）。
+/// ドット区切りの中の `/` はセグメント内の `.` を表す。
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct SysctlKey {
+    segments: Vec<String>,
+}
+
+impl SysctlKey {
+    /// ドット区切り・スラッシュ区切りのどちらのキーでもパースする
+    pub fn parse(key: &str) -> Self {
+        let slash_form = key.find(['.', '/']).is_some_and(|pos| key.as_bytes()[pos] == b'/');
+
+        let segments = if slash_form {
+            key.trim_start_matches('/').split('/').map(str::to_string).collect()
+        } else {
+            key.split('.').map(|s| s.replace('/', ".")).collect()
+        };
+
+        SysctlKey { segments }
+    }
+
+    /// セグメントの列からキーを作る
+    pub fn from_segments<I, S>(segments: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        SysctlKey {
+            segments: segments.into_iter().map(Into::into).collect(),
+        }
+    }
+
+    pub fn segments(&self) -> &[String] {
+        &self.segments
+    }
+
+    /// 正規形（ドット区切り。セグメント内の `.` は `/` で表す）
+    ///
+    /// 最初のセグメントに `.` がある場合は、ドット区切りだと `/` が先に現れてスラッシュ区切りと
+    /// 読まれてしまうため、先頭に `/` を付けたスラッシュ区切り（`/eth0.100/rp_filter`）にする。
+    /// `is_representable` なキーは `SysctlKey::parse(&key.to_dotted()) == key` になる。
+    pub fn to_dotted(&self) -> String {
+        if self.segments.first().is_some_and(|s| s.contains('.')) {
+            return format!("/{}", self.to_slashed());
+        }
+        self.segments
+            .iter()
+            .map(|s| s.replace('.', "/"))
+            .collect::<Vec<_>>()
+            .join(".")
+    }
+
+    /// キーの文字列で表せるか（セグメントが1つ以上あり、どのセグメントにも `/` を含まない）
+    pub fn is_representable(&self) -> bool {
+        !self.segments.is_empty() && self.segments.iter().all(|s| !s.contains('/'))
+    }
+
+    /// `/proc/sys` 以下のパスと同じスラッシュ区切りの形
+    pub fn to_slashed(&self) -> String {
+        self.segments.join("/")
+    }
+}
+
+/// キー文字列を正規形（ドット区切り）に変換する
+pub fn normalize_key(key: &str) -> String {
+    SysctlKey::parse(key).to_dotted()
+}
+
+impl fmt::Display for SysctlKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.to_dotted())
+    }
+}
+
+impl FromStr for SysctlKey {
+    type Err = std::convert::Infallible;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(SysctlKey::parse(s))
+    }
+}
@@ -1,11 +1,46 @@
 pub mod parser;
+pub mod options;
+pub mod document;
 pub mod converter;
 pub mod errors;
 pub mod parser_async;
 pub mod schema;
+pub mod glob;
+pub mod key;
+mod quote;
+pub mod loader;
+pub mod stream;
+pub mod borrowed;
+pub mod config;
+pub mod value;
+pub mod de;
+pub mod ser;
+pub mod export;
+pub mod import;
 
-pub use parser::{parse_str, parse_file};
-pub use converter::flatten_to_nested_json;
+pub use parser::{
+    parse_str, parse_file, parse_str_with_spans, parse_file_with_spans, parse_str_with_spans_with,
+    parse_file_with_spans_with, parse_str_recovering,
+    parse_file_recovering, parse_str_with_policy, parse_file_with_policy, DuplicatePolicy, parse_str_with,
+    parse_file_with, parse_report_with, parse_config, parse_config_with, parse_config_file,
+};
+pub use options::{ParserOptions, TrimPolicy};
+pub use document::ConfDocument;
+pub use converter::{
+    flatten_to_nested_json, try_flatten_to_nested_json, try_flatten_to_nested_json_with, flatten_to_typed_json,
+    flatten_to_typed_json_with, nested_json_to_flat, nested_json_to_flat_with, ArrayPolicy, ConflictStrategy, ConverterOptions, NullPolicy,
+};
 pub use errors::ParseError;
-pub use parser_async::parse_url_async;
-pub use schema::{SchemaType, parse_schema_str, validate_with_schema};
\ No newline at end of file
+pub use parser_async::{parse_url_async, entry_stream};
+pub use stream::{EntryReader, StreamEntry};
+pub use config::{Config, ConfigMap};
+pub use value::{ByteSize, FromConfValue};
+pub use de::{from_str, from_map};
+pub use ser::to_string;
+pub use export::{EnvOptions, to_env, to_ini, to_toml, to_yaml};
+pub use import::{from_env_prefix, from_env_vars, from_json, from_toml, from_yaml};
+pub use borrowed::{BorrowedConfig, parse_borrowed, parse_borrowed_with};
+pub use key::SysctlKey;
+pub use loader::{SysctlLoader, LoadedConfig};
+pub use glob::{KeyPattern, SysctlRules};
+pub use schema::{SchemaType, parse_schema_str, parse_schema_str_with, migrate_schema_str, validate_with_schema, validate_spanned_with_schema};
\ No newline at end of file
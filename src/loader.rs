@@ -0,0 +1,206 @@
+use std::collections::{BTreeMap, HashSet};
+use std::fs;
+use std::io;
+use std::path::{Component, Path, PathBuf};
+use crate::ParseError;
+use crate::options::ParserOptions;
+use crate::parser::{SpannedValue, parse_spanned, strip_spans};
+
+/// sysctl.d を探すディレクトリ（優先度の高い順）
+pub const SYSCTL_D_DIRS: [&str; 4] = [
+    "etc/sysctl.d",
+    "run/sysctl.d",
+    "usr/local/lib/sysctl.d",
+    "usr/lib/sysctl.d",
+];
+
+/// シンボリックリンクをたどる回数の上限
+const MAX_SYMLINK_HOPS: usize = 40;
+
+/// sysctl.d の後に読み込まれる従来の設定ファイル
+pub const SYSCTL_CONF: &str = "etc/sysctl.conf";
+
+/// systemd-sysctl と同じ優先順位で複数の設定ファイルを読み込むローダー
+///
+/// - `*.conf` ファイルをファイル名順に読み込み、後に読んだ値が優先される
+/// - 同じファイル名が複数のディレクトリにある場合は優先度の高いディレクトリのものだけを使う
+/// - `/dev/null` へのシンボリックリンクはそのファイル名をマスクする
+/// - 最後に `/etc/sysctl.conf` を読み込む（sysctl.d からシンボリックリンクされていればその位置で1回だけ）
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SysctlLoader {
+    root: PathBuf,
+    options: ParserOptions,
+}
+
+/// 読み込み結果。各キーの値と、その値を提供したファイル・行を保持する。
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct LoadedConfig {
+    /// 最終的に有効な値（`span.path` に値を提供したファイルが入る）
+    pub values: BTreeMap<String, SpannedValue>,
+    /// 読み込んだファイル（読み込んだ順）
+    pub files: Vec<PathBuf>,
+}
+
+impl LoadedConfig {
+    /// 値だけの BTreeMap に変換する
+    pub fn to_map(&self) -> BTreeMap<String, String> {
+        strip_spans(&self.values)
+    }
+
+    /// キーの値を提供したファイル
+    pub fn source_of(&self, key: &str) -> Option<&Path> {
+        self.values.get(key).and_then(|v| v.span.path.as_deref())
+    }
+}
+
+impl Default for SysctlLoader {
+    fn default() -> Self {
+        SysctlLoader::with_root("/")
+    }
+}
+
+impl SysctlLoader {
+    /// `/` をルートとしてローダーを作る
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 指定したディレクトリをルートとしてローダーを作る（テスト用の一時ディレクトリなど）
+    pub fn with_root<P: AsRef<Path>>(root: P) -> Self {
+        SysctlLoader {
+            root: root.as_ref().to_path_buf(),
+            options: ParserOptions::default(),
+        }
+    }
+
+    /// 各ファイルのパースに使うオプション（デフォルト: `parse_str` と同じ）
+    ///
+    /// `duplicate_policy` は1つのファイルの中の重複にだけ適用する。ファイルをまたぐ重複は常に後に読んだ値が優先される。
+    pub fn options(mut self, options: ParserOptions) -> Self {
+        self.options = options;
+        self
+    }
+
+    /// 読み込むファイルを (リンクのパス, `root` の中で解決したパス) として読み込み順に返す
+    fn resolved_files(&self) -> Result<Vec<(PathBuf, PathBuf)>, ParseError> {
+        // ファイル名 → 最も優先度の高いディレクトリのパス
+        let mut by_name: BTreeMap<String, PathBuf> = BTreeMap::new();
+
+        for dir in SYSCTL_D_DIRS {
+            let dir = self.root.join(dir);
+            // ディレクトリがないのは普通のこと。読めない場合は不完全な結果にせずエラーにする。
+            let read_dir = match fs::read_dir(&dir) {
+                Ok(read_dir) => read_dir,
+                Err(e) if e.kind() == io::ErrorKind::NotFound => continue,
+                Err(e) => {
+                    return Err(ParseError::InFile {
+                        path: dir.display().to_string(),
+                        error: Box::new(e.into()),
+                    });
+                }
+            };
+
+            for entry in read_dir {
+                let path = entry?.path();
+                let Some(name) = path.file_name().and_then(|n| n.to_str()) else {
+                    continue;
+                };
+                if !name.ends_with(".conf") || by_name.contains_key(name) {
+                    continue;
+                }
+                by_name.insert(name.to_string(), path);
+            }
+        }
+
+        let mut candidates: Vec<PathBuf> = by_name.into_values().filter(|path| !is_masked(path)).collect();
+        candidates.push(self.root.join(SYSCTL_CONF));
+
+        // `99-sysctl.conf -> ../sysctl.conf` のように同じファイルを指すパスは最初の1つだけ読む
+        let mut seen = HashSet::new();
+        let mut files = Vec::with_capacity(candidates.len());
+        for path in candidates {
+            let resolved = self.resolve_in_root(&path)?;
+            if resolved.is_file() && seen.insert(resolved.clone()) {
+                files.push((path, resolved));
+            }
+        }
+
+        Ok(files)
+    }
+
+    /// シンボリックリンクを `root` の中で解決する
+    ///
+    /// 絶対パスのリンク先（`/etc/sysctl.conf`）も `root` からの相対パスとして扱い、ホストのファイルは読まない。
+    /// 解決するのはファイル自身のリンクだけで、途中のディレクトリのリンクはたどらない。
+    fn resolve_in_root(&self, path: &Path) -> Result<PathBuf, ParseError> {
+        let mut current = path.to_path_buf();
+
+        for _ in 0..MAX_SYMLINK_HOPS {
+            let target = match fs::read_link(&current) {
+                Ok(target) => target,
+                // リンクではない（または存在しない）
+                Err(e) if matches!(e.kind(), io::ErrorKind::InvalidInput | io::ErrorKind::NotFound) => {
+                    return Ok(current);
+                }
+                Err(e) => return Err(e.into()),
+            };
+            let joined = match target.strip_prefix("/") {
+                Ok(relative) => self.root.join(relative),
+                Err(_) => current.parent().unwrap_or(&self.root).join(target),
+            };
+            current = self.normalize_in_root(&joined);
+        }
+
+        Err(ParseError::Io(format!("too many levels of symbolic links: {}", path.display())))
+    }
+
+    /// `..` や `.` を字句的に取り除く（`root` より上には出ない）
+    fn normalize_in_root(&self, path: &Path) -> PathBuf {
+        let Ok(relative) = path.strip_prefix(&self.root) else {
+            return path.to_path_buf();
+        };
+        let mut normalized = self.root.clone();
+        let depth = normalized.components().count();
+        for component in relative.components() {
+            match component {
+                Component::ParentDir if normalized.components().count() > depth => {
+                    normalized.pop();
+                }
+                Component::Normal(part) => normalized.push(part),
+                _ => {}
+            }
+        }
+        normalized
+    }
+
+    /// 読み込むファイルを読み込み順に返す
+    ///
+    /// シンボリックリンクのファイルはリンクのパスのまま返す（読み込むのは `root` の中で解決したファイル）。
+    pub fn config_files(&self) -> Result<Vec<PathBuf>, ParseError> {
+        Ok(self.resolved_files()?.into_iter().map(|(path, _)| path).collect())
+    }
+
+    /// すべての設定ファイルを読み込み、優先順位に従ってマージする
+    pub fn load(&self) -> Result<LoadedConfig, ParseError> {
+        let mut loaded = LoadedConfig::default();
+
+        for (path, resolved) in self.resolved_files()? {
+            let values = fs::read_to_string(&resolved)
+                .map_err(ParseError::from)
+                .and_then(|content| parse_spanned(&content, Some(&path), &self.options))
+                .map_err(|e| ParseError::InFile {
+                    path: path.display().to_string(),
+                    error: Box::new(e),
+                })?;
+            loaded.values.extend(values);
+            loaded.files.push(path);
+        }
+
+        Ok(loaded)
+    }
+}
+
+/// `/dev/null` へのシンボリックリンクかどうか
+fn is_masked(path: &Path) -> bool {
+    fs::read_link(path).is_ok_and(|target| target == Path::new("/dev/null"))
+}
@@ -0,0 +1,137 @@
+use crate::parser::DuplicatePolicy;
+
+/// キーと値の前後の空白をどう扱うか
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum TrimPolicy {
+    /// キーと値の両方の前後の空白を取り除く
+    #[default]
+    KeysAndValues,
+    /// キーだけ取り除き、値の前後の空白はそのまま残す
+    KeysOnly,
+    /// どちらも取り除かない
+    None,
+}
+
+/// パーサの挙動を指定するオプション。`ParserOptions::new()` から設定をつなげて作る。
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParserOptions {
+    pub(crate) comment_prefixes: Vec<String>,
+    pub(crate) inline_comments: bool,
+    pub(crate) quoted_values: bool,
+    pub(crate) line_continuation: bool,
+    pub(crate) max_value_length: Option<usize>,
+    pub(crate) max_lines: Option<usize>,
+    pub(crate) allow_empty_values: bool,
+    pub(crate) trim: TrimPolicy,
+    pub(crate) normalize_keys: bool,
+    pub(crate) duplicate_policy: DuplicatePolicy,
+}
+
+impl Default for ParserOptions {
+    /// `parse_str` と同じ sysctl.conf 向けの設定
+    fn default() -> Self {
+        ParserOptions {
+            comment_prefixes: vec!["#".to_string(), ";".to_string()],
+            inline_comments: true,
+            quoted_values: false,
+            line_continuation: false,
+            max_value_length: Some(4096),
+            max_lines: None,
+            allow_empty_values: true,
+            trim: TrimPolicy::KeysAndValues,
+            normalize_keys: true,
+            duplicate_policy: DuplicatePolicy::LastWins,
+        }
+    }
+}
+
+impl ParserOptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// コメント行の先頭記号（デフォルト: `#`, `;`）
+    pub fn comment_prefixes<I, S>(mut self, prefixes: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.comment_prefixes = prefixes.into_iter().map(Into::into).collect();
+        self
+    }
+
+    /// 値の後ろの `#` 以降を行末コメントとして扱うか（デフォルト: true）
+    pub fn inline_comments(mut self, enabled: bool) -> Self {
+        self.inline_comments = enabled;
+        self
+    }
+
+    /// `"..."` / `'...'` で囲んだ値とエスケープ（`\"`, `\\`, `\#`, `\n`）を解釈するか（デフォルト: false）
+    ///
+    /// 有効にすると、引用符の中や `\#` の `#` は行末コメントとして扱われない。
+    pub fn quoted_values(mut self, enabled: bool) -> Self {
+        self.quoted_values = enabled;
+        self
+    }
+
+    /// 行末の `\` で次の行とつなぐか（デフォルト: false）
+    ///
+    /// `\` とその前後の空白は1つの空白になり、つないだ後の値に最大長のチェックがかかる。
+    pub fn line_continuation(mut self, enabled: bool) -> Self {
+        self.line_continuation = enabled;
+        self
+    }
+
+    /// 値の最大バイト数（デフォルト: 4096、`None` で無制限）
+    pub fn max_value_length(mut self, max: Option<usize>) -> Self {
+        self.max_value_length = max;
+        self
+    }
+
+    /// 入力の最大行数（デフォルト: 無制限）
+    pub fn max_lines(mut self, max: Option<usize>) -> Self {
+        self.max_lines = max;
+        self
+    }
+
+    /// `key =` のような空の値を許可するか（デフォルト: true）
+    pub fn allow_empty_values(mut self, allow: bool) -> Self {
+        self.allow_empty_values = allow;
+        self
+    }
+
+    /// キーと値の前後の空白の扱い（デフォルト: 両方取り除く）
+    pub fn trim(mut self, policy: TrimPolicy) -> Self {
+        self.trim = policy;
+        self
+    }
+
+    /// `net/ipv4/ip_forward` のようなキーを正規形に揃えるか（デフォルト: true）
+    ///
+    /// sysctl 以外の形式で `/` をキーの一部として扱いたい場合は false にする。
+    pub fn normalize_keys(mut self, enabled: bool) -> Self {
+        self.normalize_keys = enabled;
+        self
+    }
+
+    /// 重複キーの扱い（デフォルト: 後勝ち）
+    pub fn duplicate_policy(mut self, policy: DuplicatePolicy) -> Self {
+        self.duplicate_policy = policy;
+        self
+    }
+
+    /// コメント行（空行を含む）かどうか
+    pub(crate) fn is_ignorable_line(&self, line: &str) -> bool {
+        let trimmed = line.trim();
+        trimmed.is_empty() || self.comment_prefixes.iter().any(|p| !p.is_empty() && trimmed.starts_with(p.as_str()))
+    }
+
+    /// 行末コメントの開始位置
+    pub(crate) fn inline_comment_pos(&self, s: &str) -> usize {
+        if self.inline_comments {
+            s.find('#').unwrap_or(s.len())
+        } else {
+            s.len()
+        }
+    }
+}
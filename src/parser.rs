@@ -1,57 +1,302 @@
 use std::collections::BTreeMap;
+use std::fmt;
 use std::fs;
-use std::path::Path;
+use std::ops::Range;
+use std::path::{Path, PathBuf};
 use crate::ParseError;
+use crate::config::Config;
+use crate::document::ConfDocument;
+use crate::options::ParserOptions;
 
-/// 空行・コメント・無視行かどうか判定
-fn is_ignorable_line(line: &str) -> bool {
-    let trimmed = line.trim();
-    trimmed.is_empty() || trimmed.starts_with('#') || trimmed.starts_with(';') || trimmed.starts_with('-')
+/// 設定値がどのファイルのどの位置に書かれていたか
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Span {
+    /// 読み込んだファイル（文字列からパースした場合は `None`）
+    pub path: Option<PathBuf>,
+    /// 1始まりの行番号（継続行の場合は開始行）
+    pub line_number: usize,
+    /// `raw_line` 内でのキーのバイト範囲（行頭の `-` は含まない）
+    pub key_range: Range<usize>,
+    /// `raw_line` 内での値のバイト範囲（引用符を含む）
+    pub value_range: Range<usize>,
+    /// 改行コードを除いた論理行の原文（継続行はつないだ行。範囲はこの文字列に対するもの）
+    pub raw_line: String,
 }
 
-/// 行末のコメントを削除（#以降を除去）
-fn remove_inline_comment(value: &str) -> &str {
-    match value.find('#') {
-        Some(pos) => &value[..pos],
-        None => value,
+/// 位置情報付きの値
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SpannedValue {
+    pub value: String,
+    /// `-key = value` 形式で書かれていた（書き込み失敗を無視する）かどうか
+    pub ignore_failure: bool,
+    /// 値を囲んでいた引用符（`ParserOptions::quoted_values` が有効な場合のみ）
+    pub quote: Option<char>,
+    pub span: Span,
+}
+
+impl Span {
+    /// キーの開始位置（1始まりの文字単位の列番号）
+    pub fn key_column(&self) -> usize {
+        self.raw_line[..self.key_range.start].chars().count() + 1
+    }
+
+    /// 値の開始位置（1始まりの文字単位の列番号）
+    pub fn value_column(&self) -> usize {
+        self.raw_line[..self.value_range.start].chars().count() + 1
+    }
+}
+
+impl fmt::Display for Span {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.path {
+            Some(path) => write!(f, "{}:{}:{}", path.display(), self.line_number, self.key_column()),
+            None => write!(f, "<input>:{}:{}", self.line_number, self.key_column()),
+        }
     }
 }
 
+/// 同じキーが複数回代入されたときの扱い
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum DuplicatePolicy {
+    /// 後に書かれた値を使う（`parse_str` と同じ）
+    #[default]
+    LastWins,
+    /// 最初に書かれた値を使う
+    FirstWins,
+    /// `ParseError::DuplicateKey` を返す
+    Error,
+    /// 後勝ちの値に加えて、すべての値を `ParseReport::all_values` に残す
+    CollectAll,
+}
+
+/// 採用されなかった代入
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ShadowedAssignment {
+    pub key: String,
+    /// 採用されなかった値
+    pub value: String,
+    /// 採用されなかった代入の行番号
+    pub line_number: usize,
+    /// 採用された代入の行番号
+    pub winning_line: usize,
+}
+
+/// 重複キーの扱いを指定したパースの結果
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ParseReport {
+    /// 採用された値
+    pub values: BTreeMap<String, String>,
+    /// `DuplicatePolicy::CollectAll` のときだけ、キーごとのすべての値（出現順）
+    pub all_values: BTreeMap<String, Vec<String>>,
+    /// 採用されなかった代入（行番号順）
+    pub shadowed: Vec<ShadowedAssignment>,
+}
+
 /// 文字列をパースして BTreeMap を返す
+///
+/// キーは正規形（ドット区切り）に揃えるため、`net/ipv4/ip_forward` と `net.ipv4.ip_forward` は同じキーになる。
 pub fn parse_str(input: &str) -> Result<BTreeMap<String, String>, ParseError> {
-    let mut map = BTreeMap::new();
+    ConfDocument::parse(input).to_map()
+}
 
-    for (i, line) in input.lines().enumerate() {
-        if is_ignorable_line(line) {
-            continue;
-        }
+/// オプションを指定して文字列をパースする（重複キーは `duplicate_policy` に従う）
+pub fn parse_str_with(input: &str, options: &ParserOptions) -> Result<BTreeMap<String, String>, ParseError> {
+    Ok(parse_report_with(input, options)?.values)
+}
 
-        let Some((raw_key, raw_value)) = line.split_once('=') else {
-            return Err(ParseError::InvalidLine {
-                line_number: i + 1,
-                content: line.to_string(),
+/// オプションを指定してファイルから読み込んでパースする
+pub fn parse_file_with<P: AsRef<Path>>(
+    path: P,
+    options: &ParserOptions,
+) -> Result<BTreeMap<String, String>, ParseError> {
+    let content = fs::read_to_string(&path)?;
+    parse_str_with(&content, options)
+}
+
+/// ファイルから読み込んでパースする
+pub fn parse_file<P: AsRef<Path>>(path: P) -> Result<BTreeMap<String, String>, ParseError> {
+    let content = fs::read_to_string(&path)?;
+    parse_str(&content)
+}
+
+/// 重複キーの扱いを指定してパースし、採用されなかった代入の一覧も返す
+pub fn parse_str_with_policy(input: &str, policy: DuplicatePolicy) -> Result<ParseReport, ParseError> {
+    parse_report_with(input, &ParserOptions::new().duplicate_policy(policy))
+}
+
+/// オプションを指定してパースし、採用されなかった代入の一覧も返す
+pub fn parse_report_with(input: &str, options: &ParserOptions) -> Result<ParseReport, ParseError> {
+    report_from_document(&ConfDocument::parse_with(input, options))
+}
+
+/// ドキュメントのオプションの `duplicate_policy` に従って値を選ぶ
+pub(crate) fn report_from_document(doc: &ConfDocument) -> Result<ParseReport, ParseError> {
+    let policy = doc.options().duplicate_policy;
+
+    // キーごとの (行番号, 値) を出現順に集める
+    let mut occurrences: BTreeMap<String, Vec<(usize, String)>> = BTreeMap::new();
+    for entry in doc.checked_entries() {
+        let (line_number, entry) = entry?;
+        let key = doc.entry_key(entry);
+        let seen = occurrences.entry(key.clone()).or_default();
+        if policy == DuplicatePolicy::Error
+            && let Some((first_line, _)) = seen.first()
+        {
+            return Err(ParseError::DuplicateKey {
+                key,
+                first_line: *first_line,
+                line_number,
             });
+        }
+        seen.push((line_number, entry.value.clone()));
+    }
+
+    let mut report = ParseReport::default();
+    for (key, seen) in occurrences {
+        let winner = match policy {
+            DuplicatePolicy::FirstWins => 0,
+            _ => seen.len() - 1,
         };
+        let winning_line = seen[winner].0;
 
-        let key = raw_key.trim();
-        let value_content = remove_inline_comment(raw_value).trim();
+        for (i, (line_number, value)) in seen.iter().enumerate() {
+            if i != winner {
+                report.shadowed.push(ShadowedAssignment {
+                    key: key.clone(),
+                    value: value.clone(),
+                    line_number: *line_number,
+                    winning_line,
+                });
+            }
+        }
+        if policy == DuplicatePolicy::CollectAll {
+            report.all_values.insert(key.clone(), seen.iter().map(|(_, v)| v.clone()).collect());
+        }
+        report.values.insert(key, seen[winner].1.clone());
+    }
+    report.shadowed.sort_by_key(|s| s.line_number);
 
-        if value_content.len() > 4096 {
-            return Err(ParseError::ValueTooLong {
-                line_number: i + 1,
-                key: key.to_string(),
-                length: value_content.len(),
-            });
+    Ok(report)
+}
+
+/// 文字列をパースし、キーが最初に現れた順序を保った `Config` を返す
+pub fn parse_config(input: &str) -> Result<Config, ParseError> {
+    ConfDocument::parse(input).to_config()
+}
+
+/// オプションを指定してパースし、キーが最初に現れた順序を保った `Config` を返す
+pub fn parse_config_with(input: &str, options: &ParserOptions) -> Result<Config, ParseError> {
+    ConfDocument::parse_with(input, options).to_config()
+}
+
+/// ファイルから読み込み、キーが最初に現れた順序を保った `Config` を返す
+pub fn parse_config_file<P: AsRef<Path>>(path: P) -> Result<Config, ParseError> {
+    let content = fs::read_to_string(&path)?;
+    parse_config(&content)
+}
+
+/// ファイルから読み込み、重複キーの扱いを指定してパースする
+pub fn parse_file_with_policy<P: AsRef<Path>>(path: P, policy: DuplicatePolicy) -> Result<ParseReport, ParseError> {
+    let content = fs::read_to_string(&path)?;
+    parse_str_with_policy(&content, policy)
+}
+
+/// 不正な行を読み飛ばしながら最後までパースし、部分的な結果とすべてのエラーを返す
+pub fn parse_str_recovering(input: &str) -> (BTreeMap<String, String>, Vec<ParseError>) {
+    let doc = ConfDocument::parse(input);
+    let mut map = BTreeMap::new();
+    let mut errors = Vec::new();
+
+    for entry in doc.checked_entries() {
+        match entry {
+            Ok((_, entry)) => {
+                map.insert(entry.canonical_key(), entry.value.clone());
+            }
+            Err(e) => errors.push(e),
         }
+    }
+
+    (map, errors)
+}
+
+/// ファイルから読み込み、不正な行を読み飛ばしながらパースする（IOエラーのみ `Err`）
+pub fn parse_file_recovering<P: AsRef<Path>>(
+    path: P,
+) -> Result<(BTreeMap<String, String>, Vec<ParseError>), ParseError> {
+    let content = fs::read_to_string(&path)?;
+    Ok(parse_str_recovering(&content))
+}
+
+pub(crate) fn parse_spanned(
+    input: &str,
+    path: Option<&Path>,
+    options: &ParserOptions,
+) -> Result<BTreeMap<String, SpannedValue>, ParseError> {
+    let doc = ConfDocument::parse_with(input, options);
+    let mut map: BTreeMap<String, SpannedValue> = BTreeMap::new();
 
-        map.insert(key.to_string(), value_content.to_string());
+    for entry in doc.checked_entries() {
+        let (line_number, entry) = entry?;
+        let key = doc.entry_key(entry);
+        if let Some(first) = map.get(&key) {
+            match options.duplicate_policy {
+                DuplicatePolicy::FirstWins => continue,
+                DuplicatePolicy::Error => {
+                    return Err(ParseError::DuplicateKey {
+                        key,
+                        first_line: first.span.line_number,
+                        line_number,
+                    });
+                }
+                DuplicatePolicy::LastWins | DuplicatePolicy::CollectAll => {}
+            }
+        }
+        let span = Span {
+            path: path.map(Path::to_path_buf),
+            line_number,
+            key_range: entry.key_range(),
+            value_range: entry.value_range(),
+            raw_line: entry.to_string(),
+        };
+        map.insert(key, SpannedValue {
+            value: entry.value.clone(),
+            ignore_failure: entry.ignore_failure,
+            quote: entry.quote,
+            span,
+        });
     }
 
     Ok(map)
 }
 
-/// ファイルから読み込んでパースする
-pub fn parse_file<P: AsRef<Path>>(path: P) -> Result<BTreeMap<String, String>, ParseError> {
+/// 文字列をパースし、各キーの位置情報と一緒に返す（重複キーは後勝ち）
+pub fn parse_str_with_spans(input: &str) -> Result<BTreeMap<String, SpannedValue>, ParseError> {
+    parse_spanned(input, None, &ParserOptions::default())
+}
+
+/// オプションを指定してパースし、各キーの位置情報と一緒に返す（重複キーは `duplicate_policy` に従う）
+pub fn parse_str_with_spans_with(
+    input: &str,
+    options: &ParserOptions,
+) -> Result<BTreeMap<String, SpannedValue>, ParseError> {
+    parse_spanned(input, None, options)
+}
+
+/// ファイルから読み込み、各キーの位置情報（ファイルパスを含む）と一緒に返す
+pub fn parse_file_with_spans<P: AsRef<Path>>(path: P) -> Result<BTreeMap<String, SpannedValue>, ParseError> {
+    parse_file_with_spans_with(path, &ParserOptions::default())
+}
+
+/// オプションを指定してファイルから読み込み、各キーの位置情報と一緒に返す
+pub fn parse_file_with_spans_with<P: AsRef<Path>>(
+    path: P,
+    options: &ParserOptions,
+) -> Result<BTreeMap<String, SpannedValue>, ParseError> {
     let content = fs::read_to_string(&path)?;
-    parse_str(&content)
+    parse_spanned(&content, Some(path.as_ref()), options)
+}
+
+/// 位置情報付きのマップから値だけの BTreeMap を作る
+pub fn strip_spans(map: &BTreeMap<String, SpannedValue>) -> BTreeMap<String, String> {
+    map.iter().map(|(k, v)| (k.clone(), v.value.clone())).collect()
 }
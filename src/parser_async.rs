@@ -1,6 +1,10 @@
 use std::collections::BTreeMap;
+use futures_util::stream::{self, Stream};
+use tokio::io::{AsyncBufRead, AsyncBufReadExt};
 use crate::ParseError;
+use crate::options::ParserOptions;
 use crate::parser::parse_str;
+use crate::stream::{LineAssembler, StreamEntry};
 
 /// 指定されたURLから設定ファイルを取得し、非同期でパースする。
 pub async fn parse_url_async(url: &str) -> Result<BTreeMap<String, String>, ParseError> {
@@ -13,3 +17,29 @@ pub async fn parse_url_async(url: &str) -> Result<BTreeMap<String, String>, Pars
 
     parse_str(&text)
 }
+
+/// `AsyncBufRead` から1行ずつ読み込み、エントリを順に返す `Stream`（`EntryReader` の非同期版）
+pub fn entry_stream<R>(reader: R, options: &ParserOptions) -> impl Stream<Item = Result<StreamEntry, ParseError>>
+where
+    R: AsyncBufRead + Unpin,
+{
+    let state = (reader, String::new(), LineAssembler::new(options));
+
+    stream::unfold(state, |(mut reader, mut buf, mut assembler)| async move {
+        while !assembler.done {
+            buf.clear();
+            let item = match reader.read_line(&mut buf).await {
+                Ok(0) => assembler.finish(),
+                Ok(_) => assembler.push(&buf),
+                Err(e) => {
+                    assembler.done = true;
+                    Some(Err(e.into()))
+                }
+            };
+            if let Some(item) = item {
+                return Some((item, (reader, buf, assembler)));
+            }
+        }
+        None
+    })
+}
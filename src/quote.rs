@@ -0,0 +1,69 @@
+//! 引用符付きの値とエスケープシーケンスの処理
+
+use std::borrow::Cow;
+
+/// 引用符として扱う文字
+pub(crate) fn is_quote(c: char) -> bool {
+    c == '"' || c == '\''
+}
+
+/// エスケープされていない `target` の位置を返す
+pub(crate) fn find_unescaped(s: &str, target: char) -> Option<usize> {
+    let mut escaped = false;
+    for (i, c) in s.char_indices() {
+        if escaped {
+            escaped = false;
+        } else if c == '\\' {
+            escaped = true;
+        } else if c == target {
+            return Some(i);
+        }
+    }
+    None
+}
+
+/// `\"`, `\'`, `\\`, `\#`, `\n` を展開する。それ以外の `\x` はそのまま残す。
+///
+/// `\` を含まない場合は入力をそのまま借用して返す。
+pub(crate) fn unescape(s: &str) -> Cow<'_, str> {
+    if !s.contains('\\') {
+        return Cow::Borrowed(s);
+    }
+    let mut out = String::with_capacity(s.len());
+    let mut chars = s.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            out.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('n') => out.push('\n'),
+            Some(next @ ('"' | '\'' | '\\' | '#')) => out.push(next),
+            Some(next) => {
+                out.push('\\');
+                out.push(next);
+            }
+            None => out.push('\\'),
+        }
+    }
+    Cow::Owned(out)
+}
+
+/// 値を引用符で囲み、必要な文字をエスケープする
+pub(crate) fn quote_value(value: &str, quote: char) -> String {
+    let mut out = String::with_capacity(value.len() + 2);
+    out.push(quote);
+    for c in value.chars() {
+        match c {
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            c if c == quote => {
+                out.push('\\');
+                out.push(c);
+            }
+            c => out.push(c),
+        }
+    }
+    out.push(quote);
+    out
+}
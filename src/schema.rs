@@ -1,5 +1,11 @@
 use std::collections::BTreeMap;
+use std::fmt;
+use serde_json::{Number, Value};
 use crate::ParseError;
+use crate::config::ConfigMap;
+use crate::document::ConfDocument;
+use crate::options::ParserOptions;
+use crate::parser::{Span, SpannedValue, strip_spans};
 
 /// スキーマの型を表す列挙型
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -9,6 +15,8 @@ pub enum SchemaType {
     Int,
     Float,
     Enum(Vec<String>),
+    /// 空白区切りの値の列（例: `int[]`）
+    List(Box<SchemaType>),
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -19,80 +27,254 @@ pub struct SchemaEntry {
 }
 
 impl SchemaType {
+    /// オプションを含まない型の表記（`int`、`string(20)`、`["a", "b"]`、`int[]` など）をパースする
+    ///
+    /// `required` や `default=` を含む表記、書式の誤りは `None`。
+    #[allow(clippy::should_implement_trait)]
     pub fn from_str(s: &str) -> Option<Self> {
-        let s = s.trim();
-
-        // string型の処理
-        // 例: string(10) のように最大文字数を指定できるようにする
-        if let Some(rest) = s.strip_prefix("string(").and_then(|s| s.strip_suffix(")")) {
-            let max_len = rest.parse::<usize>().ok();
-            return Some(SchemaType::String(max_len));
-        }
-
-        // Enum型の処理
-        if s.starts_with('[') && s.ends_with(']') {
-            let inner = &s[1..s.len() - 1];
-            let variants = inner
-                .split(',')
-                .map(|v| v.trim().trim_matches('"').to_string())
-                .filter(|s| !s.is_empty())
-                .collect::<Vec<_>>();
-            return Some(SchemaType::Enum(variants));
-        }
-
-        match s.to_lowercase().as_str() {
-            "string" => Some(SchemaType::String(None)),
-            "bool" => Some(SchemaType::Bool),
-            "int" => Some(SchemaType::Int),
-            "float" => Some(SchemaType::Float),
+        match parse_schema_value(s) {
+            Ok(SchemaEntry { typ, required: false, default: None }) => Some(typ),
             _ => None,
         }
     }
 }
 
 /// スキーマファイルをパースして BTreeMap に変換する
+///
+/// 値の書式は `型` または `型(オプション, ...)`。オプションは `required`、`default=値`、
+/// `max=N`（`string` の最大文字数）。旧来の `string(20)` は `string(max=20)` と同じ意味になる。
+/// 書式の誤りは行番号付きの `ParseError::InvalidLine` になる。
 pub fn parse_schema_str(input: &str) -> Result<BTreeMap<String, SchemaEntry>, ParseError> {
-    let raw_map = crate::parser::parse_str(input)?;
+    parse_schema_str_with(input, &ParserOptions::default())
+}
+
+/// オプション（コメント記号や重複キーの扱いなど）を指定してスキーマファイルをパースする
+pub fn parse_schema_str_with(
+    input: &str,
+    options: &ParserOptions,
+) -> Result<BTreeMap<String, SchemaEntry>, ParseError> {
     let mut schema = BTreeMap::new();
 
-    for (key, value) in raw_map {
-        let mut typ = value.trim();
-        let mut required = false;
-        let mut default = None;
-
-        // 括弧付きのメタ情報をパース（例: string(required, default=info)）
-        if let Some(idx) = value.find('(') {
-            let type_part = &value[..idx].trim();
-            let meta_part = &value[idx + 1..value.len().saturating_sub(1)].trim(); // 最後の ')'
-
-            typ = type_part;
-
-            for token in meta_part.split(',') {
-                let token = token.trim();
-                if token.eq_ignore_ascii_case("required") {
-                    required = true;
-                } else if let Some((key, value)) = token.split_once('=') {
-                    if key.trim() == "default" {
-                        default = Some(value.trim().to_string());
-                    }
-                }
+    for (key, spanned) in crate::parser::parse_str_with_spans_with(input, options)? {
+        let entry = parse_schema_value(&spanned.value).map_err(|reason| ParseError::InvalidLine {
+            line_number: spanned.span.line_number,
+            content: format!("{}: {}", key, reason),
+        })?;
+        schema.insert(key, entry);
+    }
+
+    Ok(schema)
+}
+
+/// 旧来の書式（`string(20)` など）を使ったスキーマを正規の書式（`string(max=20)`）に書き換える
+///
+/// コメントや空行、キーの順序はそのまま残す。正規の書式で書かれた行は変更しない。
+pub fn migrate_schema_str(input: &str) -> Result<String, ParseError> {
+    let schema = parse_schema_str(input)?;
+    let mut doc = ConfDocument::parse(input);
+
+    for (key, entry) in &schema {
+        let canonical = entry.to_string();
+        if doc.get(key) != Some(canonical.as_str()) {
+            doc.set(key, &canonical)?;
+        }
+    }
+
+    Ok(doc.to_string())
+}
+
+/// スキーマの値（`型(オプション, ...)`）をパースする。エラーは理由の文字列。
+fn parse_schema_value(text: &str) -> Result<SchemaEntry, String> {
+    let text = text.trim();
+    let (type_part, options) = match find_outside_quotes(text, '(') {
+        Some(open) => {
+            let close = find_outside_quotes(&text[open..], ')')
+                .map(|i| open + i)
+                .ok_or_else(|| format!("missing ')' in '{}'", text))?;
+            let trailing = text[close + 1..].trim();
+            if !trailing.is_empty() {
+                return Err(format!("unexpected '{}' after ')'", trailing));
             }
+            (&text[..open], Some(&text[open + 1..close]))
         }
+        None => (text, None),
+    };
 
-        // 型をパース
-        let schema_type = SchemaType::from_str(typ).ok_or(ParseError::InvalidLine {
-            line_number: 0,
-            content: format!("unknown schema type: {}", typ),
-        })?;
+    let mut entry = SchemaEntry {
+        typ: parse_type(type_part.trim())?,
+        required: false,
+        default: None,
+    };
+    if let Some(options) = options {
+        apply_options(&mut entry, options)?;
+    }
 
-        schema.insert(key, SchemaEntry {
-            typ: schema_type,
-            required,
-            default,
-        });
+    if let Some(default) = &entry.default
+        && !is_valid_type(default, &entry.typ)
+    {
+        return Err(format!("default value '{}' is not a valid {:?}", default, entry.typ));
     }
+    Ok(entry)
+}
 
-    Ok(schema)
+fn parse_type(s: &str) -> Result<SchemaType, String> {
+    // リスト型（例: int[]）
+    if let Some(inner) = s.strip_suffix("[]") {
+        return Ok(SchemaType::List(Box::new(parse_type(inner.trim())?)));
+    }
+
+    // Enum型（例: ["file", "console"]）
+    if let Some(rest) = s.strip_prefix('[') {
+        let inner = rest.strip_suffix(']').ok_or_else(|| format!("missing ']' in '{}'", s))?;
+        let variants = split_outside_quotes(inner, ',')
+            .into_iter()
+            .map(|v| unquote(v.trim()))
+            .collect::<Result<Vec<_>, _>>()?;
+        if variants.iter().any(String::is_empty) {
+            return Err(format!("empty enum variant in '{}'", s));
+        }
+        return Ok(SchemaType::Enum(variants));
+    }
+
+    match s.to_lowercase().as_str() {
+        "string" => Ok(SchemaType::String(None)),
+        "bool" => Ok(SchemaType::Bool),
+        "int" => Ok(SchemaType::Int),
+        "float" => Ok(SchemaType::Float),
+        "" => Err("missing schema type".to_string()),
+        _ => Err(format!("unknown schema type: {}", s)),
+    }
+}
+
+/// 括弧内のオプション（`max=20, required, default=x`）を反映する
+fn apply_options(entry: &mut SchemaEntry, options: &str) -> Result<(), String> {
+    let mut seen = Vec::new();
+
+    for token in split_outside_quotes(options, ',') {
+        let token = token.trim();
+        let (name, value) = match token.split_once('=') {
+            Some((name, value)) => (name.trim().to_ascii_lowercase(), Some(value.trim())),
+            // 旧来の `string(20)` は `max=20` として扱う
+            None if !token.is_empty() && token.bytes().all(|b| b.is_ascii_digit()) => ("max".to_string(), Some(token)),
+            None => (token.to_ascii_lowercase(), None),
+        };
+        if seen.contains(&name) {
+            return Err(format!("duplicate option '{}'", name));
+        }
+
+        match (name.as_str(), value) {
+            ("required", None) => entry.required = true,
+            ("default", Some(value)) => entry.default = Some(unquote(value)?),
+            ("max", Some(value)) => {
+                let max = value
+                    .parse::<usize>()
+                    .map_err(|_| format!("max must be a non-negative integer, got '{}'", value))?;
+                set_max_len(&mut entry.typ, max)?;
+            }
+            ("", None) => return Err(format!("empty option in '({})'", options)),
+            ("required", Some(_)) => return Err("'required' does not take a value".to_string()),
+            ("default" | "max", None) => return Err(format!("'{}' needs a value ({}=...)", name, name)),
+            _ => return Err(format!("unknown option '{}'", token)),
+        }
+        seen.push(name);
+    }
+
+    Ok(())
+}
+
+/// `string`（`string[]` なら要素）に最大文字数を設定する
+fn set_max_len(typ: &mut SchemaType, max: usize) -> Result<(), String> {
+    match typ {
+        SchemaType::String(max_len) => {
+            *max_len = Some(max);
+            Ok(())
+        }
+        SchemaType::List(inner) => set_max_len(inner, max),
+        other => Err(format!("max is only allowed for string, not {:?}", other)),
+    }
+}
+
+/// 引用符の外で最初に現れる `target` の位置
+fn find_outside_quotes(s: &str, target: char) -> Option<usize> {
+    let mut in_quote = false;
+    s.char_indices().find_map(|(i, c)| {
+        if c == '"' {
+            in_quote = !in_quote;
+        }
+        (!in_quote && c == target).then_some(i)
+    })
+}
+
+/// 引用符の外にある `separator` で分割する
+fn split_outside_quotes(s: &str, separator: char) -> Vec<&str> {
+    let mut parts = Vec::new();
+    let mut rest = s;
+    while let Some(i) = find_outside_quotes(rest, separator) {
+        parts.push(&rest[..i]);
+        rest = &rest[i + separator.len_utf8()..];
+    }
+    parts.push(rest);
+    parts
+}
+
+/// `"..."` で囲まれていれば外す（`,` や `)` を含む値を書くため）
+fn unquote(s: &str) -> Result<String, String> {
+    match s.strip_prefix('"') {
+        Some(rest) => match rest.strip_suffix('"') {
+            Some(inner) if !inner.contains('"') => Ok(inner.to_string()),
+            _ => Err(format!("unterminated quote in '{}'", s)),
+        },
+        None if s.contains('"') => Err(format!("unexpected quote in '{}'", s)),
+        None => Ok(s.to_string()),
+    }
+}
+
+/// `string(max=20, required, default=x)` のような正規の書式
+impl fmt::Display for SchemaEntry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut options = Vec::new();
+        if let Some(max) = max_len(&self.typ) {
+            options.push(format!("max={}", max));
+        }
+        if self.required {
+            options.push("required".to_string());
+        }
+        if let Some(default) = &self.default {
+            let needs_quotes = default.is_empty() || default.trim() != default || default.contains([',', '(', ')', '"']);
+            options.push(if needs_quotes { format!("default=\"{}\"", default) } else { format!("default={}", default) });
+        }
+
+        write_type(f, &self.typ)?;
+        if !options.is_empty() {
+            write!(f, "({})", options.join(", "))?;
+        }
+        Ok(())
+    }
+}
+
+fn write_type(f: &mut fmt::Formatter<'_>, typ: &SchemaType) -> fmt::Result {
+    match typ {
+        SchemaType::String(_) => f.write_str("string"),
+        SchemaType::Bool => f.write_str("bool"),
+        SchemaType::Int => f.write_str("int"),
+        SchemaType::Float => f.write_str("float"),
+        SchemaType::Enum(variants) => {
+            let quoted: Vec<String> = variants.iter().map(|v| format!("\"{}\"", v)).collect();
+            write!(f, "[{}]", quoted.join(", "))
+        }
+        SchemaType::List(inner) => {
+            write_type(f, inner)?;
+            f.write_str("[]")
+        }
+    }
+}
+
+fn max_len(typ: &SchemaType) -> Option<usize> {
+    match typ {
+        SchemaType::String(max) => *max,
+        SchemaType::List(inner) => max_len(inner),
+        _ => None,
+    }
 }
 
 fn is_valid_type(value: &str, schema_type: &SchemaType) -> bool {
@@ -101,27 +283,70 @@ fn is_valid_type(value: &str, schema_type: &SchemaType) -> bool {
         SchemaType::String(Some(max)) => value.len() <= *max,
         SchemaType::Bool => matches!(value.to_lowercase().as_str(), "true" | "false"),
         SchemaType::Int => value.parse::<i64>().is_ok(),
-        SchemaType::Float => value.parse::<f64>().is_ok(),
+        // `NaN` や `inf` は JSON の数値にできないので受け付けない
+        SchemaType::Float => value.parse::<f64>().is_ok_and(f64::is_finite),
         SchemaType::Enum(variants) => variants.iter().any(|v| v == value),
+        SchemaType::List(inner) => value.split_whitespace().all(|item| is_valid_type(item, inner)),
+    }
+}
+
+impl SchemaType {
+    /// 値をこの型の JSON 値に変換する。型に合わない値は `None`。
+    ///
+    /// `Int` と `Float` は数値、`Bool` は真偽値、`List` は配列になり、それ以外は文字列のまま。
+    pub fn to_json(&self, value: &str) -> Option<Value> {
+        if !is_valid_type(value, self) {
+            return None;
+        }
+        match self {
+            SchemaType::String(_) | SchemaType::Enum(_) => Some(Value::String(value.to_string())),
+            SchemaType::Bool => Some(Value::Bool(value.eq_ignore_ascii_case("true"))),
+            SchemaType::Int => value.parse::<i64>().ok().map(Value::from),
+            SchemaType::Float => value.parse::<f64>().ok().and_then(Number::from_f64).map(Value::Number),
+            SchemaType::List(inner) => value.split_whitespace().map(|item| inner.to_json(item)).collect(),
+        }
     }
 }
 
 /// スキーマに基づいて設定を検証し、必要に応じて default 値を補完する
-pub fn validate_with_schema(
-    config: &mut BTreeMap<String, String>,
+///
+/// `BTreeMap<String, String>` と `Config` のどちらも受け付ける。`Config` では補完した値が末尾に追加される。
+pub fn validate_with_schema<M: ConfigMap>(
+    config: &mut M,
     schema: &BTreeMap<String, SchemaEntry>,
+) -> Result<(), Vec<String>> {
+    validate_impl(config, schema, |_| None)
+}
+
+/// 位置情報付きの設定を検証し、default 値を補完した値のマップを返す
+///
+/// エラーメッセージの先頭に `path:line:column:` と、末尾に該当行の原文が付く。
+pub fn validate_spanned_with_schema(
+    config: &BTreeMap<String, SpannedValue>,
+    schema: &BTreeMap<String, SchemaEntry>,
+) -> Result<BTreeMap<String, String>, Vec<String>> {
+    let mut values = strip_spans(config);
+    validate_impl(&mut values, schema, |key| config.get(key).map(|v| &v.span))?;
+    Ok(values)
+}
+
+fn validate_impl<'a, M: ConfigMap>(
+    config: &mut M,
+    schema: &BTreeMap<String, SchemaEntry>,
+    locate: impl Fn(&str) -> Option<&'a Span>,
 ) -> Result<(), Vec<String>> {
     let mut errors = Vec::new();
 
     for (key, entry) in schema {
-        match config.get(key) {
+        match config.get_value(key) {
             Some(value) => {
                 // ✅ スキーマに基づいて値を検証
                 if !is_valid_type(value, &entry.typ) {
-                    errors.push(format!(
-                        "{}: '{}' is not a valid {:?}",
-                        key, value, entry.typ
-                    ));
+                    let message = format!("{}: '{}' is not a valid {:?}", key, value, entry.typ);
+                    errors.push(match locate(key) {
+                        Some(span) => with_location(span, &message),
+                        None => message,
+                    });
                 }
             }
             None => {
@@ -129,7 +354,7 @@ pub fn validate_with_schema(
                     errors.push(format!("{}: required field is missing", key));
                 } else if let Some(default_value) = &entry.default {
                     // ✅ default 値を補完
-                    config.insert(key.clone(), default_value.clone());
+                    config.insert_value(key.clone(), default_value.clone());
                 }
             }
         }
@@ -141,3 +366,18 @@ pub fn validate_with_schema(
         Err(errors)
     }
 }
+
+fn with_location(span: &Span, message: &str) -> String {
+    let file = match &span.path {
+        Some(path) => path.display().to_string(),
+        None => "<input>".to_string(),
+    };
+    format!(
+        "{}:{}:{}: {} (`{}`)",
+        file,
+        span.line_number,
+        span.value_column(),
+        message,
+        span.raw_line.trim()
+    )
+}
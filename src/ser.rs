@@ -0,0 +1,641 @@
+use std::collections::HashSet;
+use std::fmt::Write as _;
+use serde::ser::{self, Impossible, Serialize};
+use crate::ParseError;
+use crate::options::ParserOptions;
+use crate::key::SysctlKey;
+
+/// 構造体やマップを `a.b.c = value` 形式の文字列に書き出す
+///
+/// - 構造体のフィールドは宣言順、マップのエントリはキーの順に並ぶ
+/// - フィールド名とマップのキーは sysctl のキーとして解釈する（`"net.ipv4.ip_forward"` はネストした
+///   キーと同じ。ネストしたキーの中の `.` は `eth0/100` のように `/` で書く）
+/// - 真偽値は sysctl と同じ `1` / `0` になる
+/// - スカラーの配列は空白区切りの1つの値になる
+/// - `None` のフィールドは書き出さない
+///
+/// 配列の中の配列・構造体、文字列以外のキーを持つマップ、同じキーへの2回以上の書き出しは
+/// `ParseError::Serde`、1行に書けない値は `ParseError::InvalidValue` になる。
+pub fn to_string<T: ?Sized + Serialize>(value: &T) -> Result<String, ParseError> {
+    let mut entries = Vec::new();
+    value.serialize(EntrySerializer {
+        path: Vec::new(),
+        entries: &mut entries,
+    })?;
+
+    let options = ParserOptions::default();
+    let mut seen = HashSet::new();
+    let mut out = String::new();
+    for (key, value) in entries {
+        check_key(&options, &key)?;
+        check_value(&options, &key, &value)?;
+        if !seen.insert(key.clone()) {
+            return Err(ParseError::Serde(format!("key '{}' is written more than once", key)));
+        }
+        if value.is_empty() {
+            let _ = writeln!(out, "{} =", key);
+        } else {
+            let _ = writeln!(out, "{} = {}", key, value);
+        }
+    }
+    Ok(out)
+}
+
+/// 行頭の `-` やコメント記号、`=` などで別の行として読まれてしまうキーを拒否する
+fn check_key(options: &ParserOptions, key: &str) -> Result<(), ParseError> {
+    let ok = key.trim() == key
+        && !key.contains(['=', '#', '\n', '\r'])
+        && !key.starts_with('-')
+        && !options.is_ignorable_line(key);
+    if ok {
+        Ok(())
+    } else {
+        Err(ParseError::Serde(format!("key '{}' cannot be written as a sysctl.conf key", key)))
+    }
+}
+
+/// パースし直すと別の値になってしまう値を拒否する
+fn check_value(options: &ParserOptions, key: &str, value: &str) -> Result<(), ParseError> {
+    let reason = if value.contains(['\n', '\r']) {
+        "line breaks cannot be written as one line".to_string()
+    } else if value.contains('#') {
+        "'#' would start a comment".to_string()
+    } else if value.trim() != value {
+        "leading or trailing whitespace would be trimmed".to_string()
+    } else if let Some(max) = options.max_value_length
+        && value.len() > max
+    {
+        format!("longer than {} characters", max)
+    } else {
+        return Ok(());
+    };
+    Err(ParseError::InvalidValue {
+        key: key.to_string(),
+        value: value.to_string(),
+        reason,
+    })
+}
+
+fn unsupported(what: &str) -> ParseError {
+    ParseError::Serde(format!("{} cannot be represented in sysctl.conf format", what))
+}
+
+/// `path` の位置に値を書き出す。構造体とマップは `path` の下に展開する。
+struct EntrySerializer<'a> {
+    path: Vec<String>,
+    entries: &'a mut Vec<(String, String)>,
+}
+
+impl EntrySerializer<'_> {
+    fn push(self, value: String) -> Result<(), ParseError> {
+        if self.path.is_empty() {
+            return Err(unsupported("a top-level value that is not a struct or map"));
+        }
+        let key = SysctlKey::from_segments(self.path);
+        if !key.is_representable() {
+            return Err(ParseError::Serde(format!("key '{}' has a segment containing '/'", key.to_slashed())));
+        }
+        self.entries.push((key.to_dotted(), value));
+        Ok(())
+    }
+}
+
+impl<'a> ser::Serializer for EntrySerializer<'a> {
+    type Ok = ();
+    type Error = ParseError;
+    type SerializeSeq = SeqJoiner<'a>;
+    type SerializeTuple = SeqJoiner<'a>;
+    type SerializeTupleStruct = SeqJoiner<'a>;
+    type SerializeTupleVariant = Impossible<(), ParseError>;
+    type SerializeMap = MapEntries<'a>;
+    type SerializeStruct = MapEntries<'a>;
+    type SerializeStructVariant = Impossible<(), ParseError>;
+
+    fn serialize_bool(self, v: bool) -> Result<(), ParseError> {
+        self.push(if v { "1" } else { "0" }.to_string())
+    }
+
+    fn serialize_i64(self, v: i64) -> Result<(), ParseError> {
+        self.push(v.to_string())
+    }
+
+    fn serialize_i128(self, v: i128) -> Result<(), ParseError> {
+        self.push(v.to_string())
+    }
+
+    fn serialize_u64(self, v: u64) -> Result<(), ParseError> {
+        self.push(v.to_string())
+    }
+
+    fn serialize_u128(self, v: u128) -> Result<(), ParseError> {
+        self.push(v.to_string())
+    }
+
+    fn serialize_i8(self, v: i8) -> Result<(), ParseError> {
+        self.serialize_i64(v.into())
+    }
+
+    fn serialize_i16(self, v: i16) -> Result<(), ParseError> {
+        self.serialize_i64(v.into())
+    }
+
+    fn serialize_i32(self, v: i32) -> Result<(), ParseError> {
+        self.serialize_i64(v.into())
+    }
+
+    fn serialize_u8(self, v: u8) -> Result<(), ParseError> {
+        self.serialize_u64(v.into())
+    }
+
+    fn serialize_u16(self, v: u16) -> Result<(), ParseError> {
+        self.serialize_u64(v.into())
+    }
+
+    fn serialize_u32(self, v: u32) -> Result<(), ParseError> {
+        self.serialize_u64(v.into())
+    }
+
+    fn serialize_f32(self, v: f32) -> Result<(), ParseError> {
+        self.push(v.to_string())
+    }
+
+    fn serialize_f64(self, v: f64) -> Result<(), ParseError> {
+        self.push(v.to_string())
+    }
+
+    fn serialize_char(self, v: char) -> Result<(), ParseError> {
+        self.push(v.to_string())
+    }
+
+    fn serialize_str(self, v: &str) -> Result<(), ParseError> {
+        self.push(v.to_string())
+    }
+
+    fn serialize_bytes(self, _v: &[u8]) -> Result<(), ParseError> {
+        Err(unsupported("a byte array"))
+    }
+
+    fn serialize_none(self) -> Result<(), ParseError> {
+        Ok(())
+    }
+
+    fn serialize_some<T: ?Sized + Serialize>(self, value: &T) -> Result<(), ParseError> {
+        value.serialize(self)
+    }
+
+    fn serialize_unit(self) -> Result<(), ParseError> {
+        Ok(())
+    }
+
+    fn serialize_unit_struct(self, _name: &'static str) -> Result<(), ParseError> {
+        Ok(())
+    }
+
+    fn serialize_unit_variant(self, _name: &'static str, _index: u32, variant: &'static str) -> Result<(), ParseError> {
+        self.push(variant.to_string())
+    }
+
+    fn serialize_newtype_struct<T: ?Sized + Serialize>(self, _name: &'static str, value: &T) -> Result<(), ParseError> {
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_variant<T: ?Sized + Serialize>(
+        self,
+        _name: &'static str,
+        _index: u32,
+        _variant: &'static str,
+        _value: &T,
+    ) -> Result<(), ParseError> {
+        Err(unsupported("an enum variant with data"))
+    }
+
+    fn serialize_seq(self, len: Option<usize>) -> Result<SeqJoiner<'a>, ParseError> {
+        Ok(SeqJoiner {
+            parent: self,
+            items: Vec::with_capacity(len.unwrap_or(0)),
+        })
+    }
+
+    fn serialize_tuple(self, len: usize) -> Result<SeqJoiner<'a>, ParseError> {
+        self.serialize_seq(Some(len))
+    }
+
+    fn serialize_tuple_struct(self, _name: &'static str, len: usize) -> Result<SeqJoiner<'a>, ParseError> {
+        self.serialize_seq(Some(len))
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        _name: &'static str,
+        _index: u32,
+        _variant: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeTupleVariant, ParseError> {
+        Err(unsupported("an enum variant with data"))
+    }
+
+    fn serialize_map(self, _len: Option<usize>) -> Result<MapEntries<'a>, ParseError> {
+        Ok(MapEntries {
+            path: self.path,
+            entries: self.entries,
+            pending: Vec::new(),
+            next_key: None,
+            sorted: true,
+        })
+    }
+
+    fn serialize_struct(self, _name: &'static str, _len: usize) -> Result<MapEntries<'a>, ParseError> {
+        Ok(MapEntries {
+            path: self.path,
+            entries: self.entries,
+            pending: Vec::new(),
+            next_key: None,
+            sorted: false,
+        })
+    }
+
+    fn serialize_struct_variant(
+        self,
+        _name: &'static str,
+        _index: u32,
+        _variant: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeStructVariant, ParseError> {
+        Err(unsupported("an enum variant with data"))
+    }
+}
+
+/// 配列の要素を空白区切りの1つの値にまとめる
+struct SeqJoiner<'a> {
+    parent: EntrySerializer<'a>,
+    items: Vec<String>,
+}
+
+impl SeqJoiner<'_> {
+    fn push_item<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<(), ParseError> {
+        let item = value.serialize(ScalarSerializer)?;
+        if item.is_empty() || item.contains(char::is_whitespace) {
+            return Err(unsupported(&format!("a sequence item '{}' that is empty or contains whitespace", item)));
+        }
+        self.items.push(item);
+        Ok(())
+    }
+
+    fn finish(self) -> Result<(), ParseError> {
+        self.parent.push(self.items.join(" "))
+    }
+}
+
+impl ser::SerializeSeq for SeqJoiner<'_> {
+    type Ok = ();
+    type Error = ParseError;
+
+    fn serialize_element<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<(), ParseError> {
+        self.push_item(value)
+    }
+
+    fn end(self) -> Result<(), ParseError> {
+        self.finish()
+    }
+}
+
+impl ser::SerializeTuple for SeqJoiner<'_> {
+    type Ok = ();
+    type Error = ParseError;
+
+    fn serialize_element<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<(), ParseError> {
+        self.push_item(value)
+    }
+
+    fn end(self) -> Result<(), ParseError> {
+        self.finish()
+    }
+}
+
+impl ser::SerializeTupleStruct for SeqJoiner<'_> {
+    type Ok = ();
+    type Error = ParseError;
+
+    fn serialize_field<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<(), ParseError> {
+        self.push_item(value)
+    }
+
+    fn end(self) -> Result<(), ParseError> {
+        self.finish()
+    }
+}
+
+/// 構造体のフィールドやマップのエントリを `path` の下に展開する
+///
+/// マップは HashMap などでも出力が安定するように、キーの順に並べ替えてから書き出す。
+struct MapEntries<'a> {
+    path: Vec<String>,
+    entries: &'a mut Vec<(String, String)>,
+    /// (キーのセグメント, そのキーの下に書き出したエントリ)
+    pending: Vec<(String, Vec<(String, String)>)>,
+    next_key: Option<String>,
+    sorted: bool,
+}
+
+impl MapEntries<'_> {
+    /// キーを sysctl のキーとしてセグメントに分割して `path` に足す
+    ///
+    /// 最上位のキーはドット区切り・スラッシュ区切りのどちらでもよい。ネストしたキーはドット区切りの
+    /// 続きとして扱い、セグメント内の `.` は `/` で書く（`rp_filter` の下の `eth0/100`）。
+    fn push_field<T: ?Sized + Serialize>(&mut self, key: String, value: &T) -> Result<(), ParseError> {
+        let mut path = self.path.clone();
+        if path.is_empty() {
+            path.extend(SysctlKey::parse(&key).segments().iter().cloned());
+        } else {
+            path.extend(key.split('.').map(|segment| segment.replace('/', ".")));
+        }
+        let mut entries = Vec::new();
+        value.serialize(EntrySerializer {
+            path,
+            entries: &mut entries,
+        })?;
+        self.pending.push((key, entries));
+        Ok(())
+    }
+
+    fn finish(mut self) -> Result<(), ParseError> {
+        if self.sorted {
+            self.pending.sort_by(|(a, _), (b, _)| a.cmp(b));
+        }
+        self.entries.extend(self.pending.into_iter().flat_map(|(_, entries)| entries));
+        Ok(())
+    }
+}
+
+impl ser::SerializeMap for MapEntries<'_> {
+    type Ok = ();
+    type Error = ParseError;
+
+    fn serialize_key<T: ?Sized + Serialize>(&mut self, key: &T) -> Result<(), ParseError> {
+        self.next_key = Some(key.serialize(KeySerializer)?);
+        Ok(())
+    }
+
+    fn serialize_value<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<(), ParseError> {
+        let key = self.next_key.take().expect("serialize_value called before serialize_key");
+        self.push_field(key, value)
+    }
+
+    fn end(self) -> Result<(), ParseError> {
+        self.finish()
+    }
+}
+
+impl ser::SerializeStruct for MapEntries<'_> {
+    type Ok = ();
+    type Error = ParseError;
+
+    fn serialize_field<T: ?Sized + Serialize>(&mut self, key: &'static str, value: &T) -> Result<(), ParseError> {
+        self.push_field(key.to_string(), value)
+    }
+
+    fn end(self) -> Result<(), ParseError> {
+        self.finish()
+    }
+}
+
+/// スカラー以外を拒否するメソッドをまとめて定義する
+macro_rules! reject_compound {
+    ($what:literal) => {
+        fn serialize_bytes(self, _v: &[u8]) -> Result<String, ParseError> {
+            Err(unsupported("a byte array"))
+        }
+
+        fn serialize_none(self) -> Result<String, ParseError> {
+            Err(unsupported($what))
+        }
+
+        fn serialize_some<T: ?Sized + Serialize>(self, value: &T) -> Result<String, ParseError> {
+            value.serialize(self)
+        }
+
+        fn serialize_unit(self) -> Result<String, ParseError> {
+            Err(unsupported($what))
+        }
+
+        fn serialize_unit_struct(self, _name: &'static str) -> Result<String, ParseError> {
+            Err(unsupported($what))
+        }
+
+        fn serialize_newtype_struct<T: ?Sized + Serialize>(
+            self,
+            _name: &'static str,
+            value: &T,
+        ) -> Result<String, ParseError> {
+            value.serialize(self)
+        }
+
+        fn serialize_newtype_variant<T: ?Sized + Serialize>(
+            self,
+            _name: &'static str,
+            _index: u32,
+            _variant: &'static str,
+            _value: &T,
+        ) -> Result<String, ParseError> {
+            Err(unsupported($what))
+        }
+
+        fn serialize_seq(self, _len: Option<usize>) -> Result<Self::SerializeSeq, ParseError> {
+            Err(unsupported($what))
+        }
+
+        fn serialize_tuple(self, _len: usize) -> Result<Self::SerializeTuple, ParseError> {
+            Err(unsupported($what))
+        }
+
+        fn serialize_tuple_struct(
+            self,
+            _name: &'static str,
+            _len: usize,
+        ) -> Result<Self::SerializeTupleStruct, ParseError> {
+            Err(unsupported($what))
+        }
+
+        fn serialize_tuple_variant(
+            self,
+            _name: &'static str,
+            _index: u32,
+            _variant: &'static str,
+            _len: usize,
+        ) -> Result<Self::SerializeTupleVariant, ParseError> {
+            Err(unsupported($what))
+        }
+
+        fn serialize_map(self, _len: Option<usize>) -> Result<Self::SerializeMap, ParseError> {
+            Err(unsupported($what))
+        }
+
+        fn serialize_struct(self, _name: &'static str, _len: usize) -> Result<Self::SerializeStruct, ParseError> {
+            Err(unsupported($what))
+        }
+
+        fn serialize_struct_variant(
+            self,
+            _name: &'static str,
+            _index: u32,
+            _variant: &'static str,
+            _len: usize,
+        ) -> Result<Self::SerializeStructVariant, ParseError> {
+            Err(unsupported($what))
+        }
+    };
+}
+
+/// 配列の要素（スカラーのみ）を文字列にする
+struct ScalarSerializer;
+
+impl ser::Serializer for ScalarSerializer {
+    type Ok = String;
+    type Error = ParseError;
+    type SerializeSeq = Impossible<String, ParseError>;
+    type SerializeTuple = Impossible<String, ParseError>;
+    type SerializeTupleStruct = Impossible<String, ParseError>;
+    type SerializeTupleVariant = Impossible<String, ParseError>;
+    type SerializeMap = Impossible<String, ParseError>;
+    type SerializeStruct = Impossible<String, ParseError>;
+    type SerializeStructVariant = Impossible<String, ParseError>;
+
+    fn serialize_bool(self, v: bool) -> Result<String, ParseError> {
+        Ok(if v { "1" } else { "0" }.to_string())
+    }
+
+    fn serialize_i64(self, v: i64) -> Result<String, ParseError> {
+        Ok(v.to_string())
+    }
+
+    fn serialize_i128(self, v: i128) -> Result<String, ParseError> {
+        Ok(v.to_string())
+    }
+
+    fn serialize_u64(self, v: u64) -> Result<String, ParseError> {
+        Ok(v.to_string())
+    }
+
+    fn serialize_u128(self, v: u128) -> Result<String, ParseError> {
+        Ok(v.to_string())
+    }
+
+    fn serialize_i8(self, v: i8) -> Result<String, ParseError> {
+        self.serialize_i64(v.into())
+    }
+
+    fn serialize_i16(self, v: i16) -> Result<String, ParseError> {
+        self.serialize_i64(v.into())
+    }
+
+    fn serialize_i32(self, v: i32) -> Result<String, ParseError> {
+        self.serialize_i64(v.into())
+    }
+
+    fn serialize_u8(self, v: u8) -> Result<String, ParseError> {
+        self.serialize_u64(v.into())
+    }
+
+    fn serialize_u16(self, v: u16) -> Result<String, ParseError> {
+        self.serialize_u64(v.into())
+    }
+
+    fn serialize_u32(self, v: u32) -> Result<String, ParseError> {
+        self.serialize_u64(v.into())
+    }
+
+    fn serialize_f32(self, v: f32) -> Result<String, ParseError> {
+        Ok(v.to_string())
+    }
+
+    fn serialize_f64(self, v: f64) -> Result<String, ParseError> {
+        Ok(v.to_string())
+    }
+
+    fn serialize_char(self, v: char) -> Result<String, ParseError> {
+        Ok(v.to_string())
+    }
+
+    fn serialize_str(self, v: &str) -> Result<String, ParseError> {
+        Ok(v.to_string())
+    }
+
+    fn serialize_unit_variant(self, _name: &'static str, _index: u32, variant: &'static str) -> Result<String, ParseError> {
+        Ok(variant.to_string())
+    }
+
+    reject_compound!("a nested sequence or struct inside a sequence");
+}
+
+/// マップのキー（文字列のみ）を取り出す
+struct KeySerializer;
+
+impl ser::Serializer for KeySerializer {
+    type Ok = String;
+    type Error = ParseError;
+    type SerializeSeq = Impossible<String, ParseError>;
+    type SerializeTuple = Impossible<String, ParseError>;
+    type SerializeTupleStruct = Impossible<String, ParseError>;
+    type SerializeTupleVariant = Impossible<String, ParseError>;
+    type SerializeMap = Impossible<String, ParseError>;
+    type SerializeStruct = Impossible<String, ParseError>;
+    type SerializeStructVariant = Impossible<String, ParseError>;
+
+    fn serialize_str(self, v: &str) -> Result<String, ParseError> {
+        Ok(v.to_string())
+    }
+
+    fn serialize_char(self, v: char) -> Result<String, ParseError> {
+        Ok(v.to_string())
+    }
+
+    fn serialize_unit_variant(self, _name: &'static str, _index: u32, variant: &'static str) -> Result<String, ParseError> {
+        Ok(variant.to_string())
+    }
+
+    fn serialize_bool(self, _v: bool) -> Result<String, ParseError> {
+        Err(unsupported("a map with non-string keys"))
+    }
+
+    fn serialize_i64(self, _v: i64) -> Result<String, ParseError> {
+        Err(unsupported("a map with non-string keys"))
+    }
+
+    fn serialize_u64(self, _v: u64) -> Result<String, ParseError> {
+        Err(unsupported("a map with non-string keys"))
+    }
+
+    fn serialize_i8(self, v: i8) -> Result<String, ParseError> {
+        self.serialize_i64(v.into())
+    }
+
+    fn serialize_i16(self, v: i16) -> Result<String, ParseError> {
+        self.serialize_i64(v.into())
+    }
+
+    fn serialize_i32(self, v: i32) -> Result<String, ParseError> {
+        self.serialize_i64(v.into())
+    }
+
+    fn serialize_u8(self, v: u8) -> Result<String, ParseError> {
+        self.serialize_u64(v.into())
+    }
+
+    fn serialize_u16(self, v: u16) -> Result<String, ParseError> {
+        self.serialize_u64(v.into())
+    }
+
+    fn serialize_u32(self, v: u32) -> Result<String, ParseError> {
+        self.serialize_u64(v.into())
+    }
+
+    fn serialize_f32(self, _v: f32) -> Result<String, ParseError> {
+        Err(unsupported("a map with non-string keys"))
+    }
+
+    fn serialize_f64(self, _v: f64) -> Result<String, ParseError> {
+        Err(unsupported("a map with non-string keys"))
+    }
+
+    reject_compound!("a map with non-string keys");
+}
@@ -0,0 +1,169 @@
+use std::borrow::Cow;
+use std::io::BufRead;
+use crate::ParseError;
+use crate::document::{LineKind, check_entry, ends_with_continuation, join_continuation, tokenize_line};
+use crate::key::normalize_key;
+use crate::options::ParserOptions;
+
+/// ストリーミングパーサが返す1エントリ
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StreamEntry {
+    /// エントリの書かれた行番号（継続行の場合は開始行）
+    pub line_number: usize,
+    pub key: String,
+    pub value: String,
+    /// `-key = value` の形で書かれていたか
+    pub ignore_failure: bool,
+}
+
+/// `BufRead` から1行ずつ読み込み、エントリを順に返すイテレータ
+///
+/// 入力全体をメモリに持たないので、パイプで流れてくる `sysctl -a` の出力などにも使える。
+/// 不正な行はエラーとして返し、続きの行の読み込みは継続する。
+/// 読み込みエラーと `max_lines` 超過の後は何も返さない。
+pub struct EntryReader<R> {
+    reader: R,
+    buf: String,
+    assembler: LineAssembler,
+}
+
+impl<R: BufRead> EntryReader<R> {
+    /// デフォルトのオプションで読み込む
+    pub fn new(reader: R) -> Self {
+        Self::with_options(reader, &ParserOptions::default())
+    }
+
+    pub fn with_options(reader: R, options: &ParserOptions) -> Self {
+        EntryReader {
+            reader,
+            buf: String::new(),
+            assembler: LineAssembler::new(options),
+        }
+    }
+}
+
+impl<R: BufRead> Iterator for EntryReader<R> {
+    type Item = Result<StreamEntry, ParseError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while !self.assembler.done {
+            self.buf.clear();
+            match self.reader.read_line(&mut self.buf) {
+                Ok(0) => return self.assembler.finish(),
+                Ok(_) => {
+                    if let Some(item) = self.assembler.push(&self.buf) {
+                        return Some(item);
+                    }
+                }
+                Err(e) => {
+                    self.assembler.done = true;
+                    return Some(Err(e.into()));
+                }
+            }
+        }
+        None
+    }
+}
+
+/// 物理行を受け取って論理行（継続行をつないだ行）にまとめ、エントリに変換する
+///
+/// 同期版の `EntryReader`、非同期版の `entry_stream`、`parse_borrowed_with` で共有する。
+pub(crate) struct LineAssembler {
+    options: ParserOptions,
+    line_number: usize,
+    start: usize,
+    pending: Option<String>,
+    pub(crate) done: bool,
+}
+
+impl LineAssembler {
+    pub(crate) fn new(options: &ParserOptions) -> Self {
+        LineAssembler {
+            options: options.clone(),
+            line_number: 0,
+            start: 0,
+            pending: None,
+            done: false,
+        }
+    }
+
+    /// 物理行（改行を含んでもよい）を1つ渡す。エントリかエラーが確定したら返す。
+    pub(crate) fn push(&mut self, raw: &str) -> Option<Result<StreamEntry, ParseError>> {
+        match self.push_line(raw)? {
+            Ok((_, line)) => self.entry(&line),
+            Err(e) => Some(Err(e)),
+        }
+    }
+
+    /// 入力の終わり。継続の途中の行が残っていればそれを返す。
+    pub(crate) fn finish(&mut self) -> Option<Result<StreamEntry, ParseError>> {
+        let (_, line) = self.finish_line()?;
+        self.entry(&line)
+    }
+
+    /// 物理行を1つ渡し、論理行が確定したら (開始行番号, 行) を返す
+    ///
+    /// 継続していない行は渡された行を借用したまま返す。
+    pub(crate) fn push_line<'a>(&mut self, raw: &'a str) -> Option<Result<(usize, Cow<'a, str>), ParseError>> {
+        let has_newline = raw.ends_with('\n');
+        let body = raw.strip_suffix('\n').unwrap_or(raw);
+        let body = body.strip_suffix('\r').unwrap_or(body);
+        self.line_number += 1;
+
+        if let Some(max) = self.options.max_lines
+            && self.line_number > max
+        {
+            self.done = true;
+            return Some(Err(ParseError::TooManyLines {
+                line_number: self.line_number,
+                limit: max,
+            }));
+        }
+
+        let line = match self.pending.take() {
+            Some(mut joined) => {
+                join_continuation(&mut joined, body);
+                Cow::Owned(joined)
+            }
+            None => {
+                self.start = self.line_number;
+                // コメント行・空行は継続しない
+                if !self.options.line_continuation || self.options.is_ignorable_line(body) {
+                    return Some(Ok((self.start, Cow::Borrowed(body))));
+                }
+                Cow::Borrowed(body)
+            }
+        };
+
+        if self.options.line_continuation && has_newline && ends_with_continuation(&line) {
+            self.pending = Some(line.into_owned());
+            return None;
+        }
+        Some(Ok((self.start, line)))
+    }
+
+    /// 入力の終わり。継続の途中の論理行が残っていれば (開始行番号, 行) を返す。
+    pub(crate) fn finish_line(&mut self) -> Option<(usize, String)> {
+        self.done = true;
+        let line = self.pending.take()?;
+        Some((self.start, line))
+    }
+
+    fn entry(&self, line: &str) -> Option<Result<StreamEntry, ParseError>> {
+        let line_number = self.start;
+        match tokenize_line(line, &self.options) {
+            LineKind::Entry(entry) => Some(check_entry(&self.options, line_number, &entry).map(|()| StreamEntry {
+                line_number,
+                key: if self.options.normalize_keys {
+                    normalize_key(&entry.key)
+                } else {
+                    entry.key
+                },
+                value: entry.value,
+                ignore_failure: entry.ignore_failure,
+            })),
+            LineKind::Invalid(content) => Some(Err(ParseError::InvalidLine { line_number, content })),
+            LineKind::Blank(_) | LineKind::Comment(_) | LineKind::Exclusion(_) => None,
+        }
+    }
+}
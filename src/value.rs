@@ -0,0 +1,144 @@
+use std::fmt;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+use std::time::Duration;
+
+/// 設定値の文字列から変換できる型
+///
+/// 変換に失敗した場合は理由を返す。キーを含むエラーは `Config::get` 側で `ParseError::InvalidValue` にする。
+pub trait FromConfValue: Sized {
+    fn from_conf_value(value: &str) -> Result<Self, String>;
+}
+
+impl FromConfValue for String {
+    fn from_conf_value(value: &str) -> Result<Self, String> {
+        Ok(value.to_string())
+    }
+}
+
+macro_rules! impl_from_str {
+    ($expected:literal: $($t:ty),*) => {
+        $(
+            impl FromConfValue for $t {
+                fn from_conf_value(value: &str) -> Result<Self, String> {
+                    value.trim().parse().map_err(|e| format!("expected {}: {}", $expected, e))
+                }
+            }
+        )*
+    };
+}
+
+impl_from_str!("integer": i8, i16, i32, i64, i128, isize, u8, u16, u32, u64, u128, usize);
+impl_from_str!("float": f32, f64);
+impl_from_str!("IP address": IpAddr, Ipv4Addr, Ipv6Addr);
+
+/// `1`/`0`, `yes`/`no`, `true`/`false`（大文字小文字は区別しない）
+impl FromConfValue for bool {
+    fn from_conf_value(value: &str) -> Result<Self, String> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "1" | "yes" | "true" => Ok(true),
+            "0" | "no" | "false" => Ok(false),
+            _ => Err("expected boolean (1/0, yes/no, true/false)".to_string()),
+        }
+    }
+}
+
+/// `30s`, `500ms`, `1h30m` のような期間。単位のない数値は秒として扱う。
+///
+/// 単位: `ns`, `us`, `ms`, `s`/`sec`, `m`/`min`, `h`/`hr`, `d`
+impl FromConfValue for Duration {
+    fn from_conf_value(value: &str) -> Result<Self, String> {
+        let value = value.trim();
+        if let Ok(secs) = value.parse::<u64>() {
+            return Ok(Duration::from_secs(secs));
+        }
+
+        // u32 に収まらない値（`5000000000ns` など）も扱えるよう、ナノ秒の u128 で足し合わせる
+        let mut total_nanos: u128 = 0;
+        for (number, unit) in split_units(value).ok_or_else(|| format!("expected duration: '{}'", value))? {
+            let nanos_per_unit: u128 = match unit {
+                "ns" => 1,
+                "us" => 1_000,
+                "ms" => 1_000_000,
+                "s" | "sec" => 1_000_000_000,
+                "m" | "min" => 60 * 1_000_000_000,
+                "h" | "hr" => 60 * 60 * 1_000_000_000,
+                "d" => 24 * 60 * 60 * 1_000_000_000,
+                _ => return Err(format!("expected duration: unknown unit '{}'", unit)),
+            };
+            total_nanos = u128::from(number)
+                .checked_mul(nanos_per_unit)
+                .and_then(|n| total_nanos.checked_add(n))
+                .ok_or_else(|| "expected duration: value is too large".to_string())?;
+        }
+
+        let secs = u64::try_from(total_nanos / 1_000_000_000)
+            .map_err(|_| "expected duration: value is too large".to_string())?;
+        Ok(Duration::new(secs, (total_nanos % 1_000_000_000) as u32))
+    }
+}
+
+/// `4096`, `16K`, `1MiB` のようなバイト数（`K`/`KB`/`KiB` はいずれも 1024 倍）
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct ByteSize(pub u64);
+
+impl ByteSize {
+    pub fn as_u64(self) -> u64 {
+        self.0
+    }
+}
+
+impl fmt::Display for ByteSize {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl FromConfValue for ByteSize {
+    fn from_conf_value(value: &str) -> Result<Self, String> {
+        let value = value.trim();
+        let digits = value.find(|c: char| !c.is_ascii_digit()).unwrap_or(value.len());
+        let number: u64 = value[..digits]
+            .parse()
+            .map_err(|_| format!("expected byte size: '{}'", value))?;
+
+        let shift = match value[digits..].trim_start().to_ascii_uppercase().as_str() {
+            "" | "B" => 0,
+            "K" | "KB" | "KIB" => 10,
+            "M" | "MB" | "MIB" => 20,
+            "G" | "GB" | "GIB" => 30,
+            "T" | "TB" | "TIB" => 40,
+            unit => return Err(format!("expected byte size: unknown unit '{}'", unit)),
+        };
+        number
+            .checked_mul(1 << shift)
+            .map(ByteSize)
+            .ok_or_else(|| "expected byte size: value is too large".to_string())
+    }
+}
+
+/// 空白区切りの値（`net.ipv4.tcp_rmem = 4096 87380 6291456` など）
+impl<T: FromConfValue> FromConfValue for Vec<T> {
+    fn from_conf_value(value: &str) -> Result<Self, String> {
+        value.split_whitespace().map(T::from_conf_value).collect()
+    }
+}
+
+/// `1h30m` を `[(1, "h"), (30, "m")]` に分ける
+fn split_units(s: &str) -> Option<Vec<(u64, &str)>> {
+    let mut parts = Vec::new();
+    let mut rest = s;
+    while !rest.is_empty() {
+        let digits = rest.find(|c: char| !c.is_ascii_digit()).unwrap_or(rest.len());
+        let number = rest[..digits].parse().ok()?;
+        let unit_len = rest[digits..]
+            .find(|c: char| c.is_ascii_digit())
+            .unwrap_or(rest.len() - digits);
+        let unit = rest[digits..digits + unit_len].trim();
+        if unit.is_empty() {
+            return None;
+        }
+        parts.push((number, unit));
+        rest = rest[digits + unit_len..].trim_start();
+    }
+    (!parts.is_empty()).then_some(parts)
+}
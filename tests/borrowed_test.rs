@@ -0,0 +1,64 @@
+use std::borrow::Cow;
+use confparser::{DuplicatePolicy, ParseError, ParserOptions, parse_borrowed, parse_borrowed_with, parse_str, parse_str_with};
+
+const INPUT: &str = "# comment\nnet.ipv4.ip_forward = 1  # inline\nnet/ipv4/conf/eth0.100/rp_filter = 2\nlog.file = /var/log/console.log\n";
+
+#[test]
+fn test_parse_borrowed_matches_parse_str() {
+    let config = parse_borrowed(INPUT).unwrap();
+
+    assert_eq!(config.len(), 3);
+    assert_eq!(config.get("net.ipv4.ip_forward"), Some("1"));
+    assert_eq!(config.get("net.ipv4.conf.eth0/100.rp_filter"), Some("2"));
+    assert_eq!(config.into_owned(), parse_str(INPUT).unwrap());
+}
+
+#[test]
+fn test_parse_borrowed_borrows_from_input() {
+    let config = parse_borrowed(INPUT).unwrap();
+    let map = config.as_map();
+
+    assert!(matches!(map.get("net.ipv4.ip_forward"), Some(Cow::Borrowed("1"))));
+    assert!(matches!(map.get("log.file"), Some(Cow::Borrowed("/var/log/console.log"))));
+    // スラッシュ区切りのキーだけは正規化のために新しい文字列になる
+    let (key, value) = map.iter().find(|(k, _)| k.contains("eth0")).unwrap();
+    assert!(matches!(key, Cow::Owned(_)));
+    assert!(matches!(value, Cow::Borrowed("2")));
+}
+
+#[test]
+fn test_parse_borrowed_unescapes_only_when_needed() {
+    let input = "a = \"plain\"\nb = \"say \\\"hi\\\"\"\n";
+    let options = ParserOptions::new().quoted_values(true);
+
+    let config = parse_borrowed_with(input, &options).unwrap();
+
+    assert!(matches!(config.as_map().get("a"), Some(Cow::Borrowed("plain"))));
+    assert!(matches!(config.as_map().get("b"), Some(Cow::Owned(v)) if v == "say \"hi\""));
+    assert_eq!(config.into_owned(), parse_str_with(input, &options).unwrap());
+}
+
+#[test]
+fn test_parse_borrowed_reports_errors() {
+    assert!(matches!(
+        parse_borrowed("a = 1\ninvalid line\n"),
+        Err(ParseError::InvalidLine { line_number: 2, .. })
+    ));
+
+    let options = ParserOptions::new().duplicate_policy(DuplicatePolicy::Error);
+    assert!(matches!(
+        parse_borrowed_with("a = 1\nb = 2\na = 3\n", &options),
+        Err(ParseError::DuplicateKey { first_line: 1, line_number: 3, .. })
+    ));
+}
+
+#[test]
+fn test_parse_borrowed_with_line_continuation() {
+    let input = "a = 1 \\\n  2\nb = 3\n";
+    let options = ParserOptions::new().line_continuation(true);
+
+    let config = parse_borrowed_with(input, &options).unwrap();
+
+    assert_eq!(config.get("a"), Some("1 2"));
+    assert_eq!(config.into_owned(), parse_str_with(input, &options).unwrap());
+}
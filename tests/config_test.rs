@@ -0,0 +1,89 @@
+use std::collections::BTreeMap;
+use confparser::schema::SchemaEntry;
+use confparser::{
+    Config, DuplicatePolicy, ParseError, ParserOptions, SchemaType, flatten_to_nested_json, parse_config,
+    parse_config_with, parse_str, validate_with_schema,
+};
+
+const INPUT: &str = "\
+log.name = default.log
+endpoint = localhost:3000
+log.file = /var/log/console.log
+db.port = 5432
+log.name = app.log
+";
+
+#[test]
+fn test_parse_config_keeps_first_appearance_order() {
+    let config = parse_config(INPUT).unwrap();
+
+    assert_eq!(config.keys().collect::<Vec<_>>(), vec!["log.name", "endpoint", "log.file", "db.port"]);
+    assert_eq!(config.get_str("log.name"), Some("app.log"));
+    assert_eq!(config.get_str("missing"), None);
+}
+
+#[test]
+fn test_config_converts_to_btree_map() {
+    let config = parse_config(INPUT).unwrap();
+
+    assert_eq!(config.to_btree_map(), parse_str(INPUT).unwrap());
+    assert_eq!(BTreeMap::from(config.clone()), parse_str(INPUT).unwrap());
+    assert_eq!(Config::from(parse_str(INPUT).unwrap()).len(), config.len());
+}
+
+#[test]
+fn test_parse_config_with_duplicate_policy() {
+    let first_wins = ParserOptions::new().duplicate_policy(DuplicatePolicy::FirstWins);
+    let config = parse_config_with(INPUT, &first_wins).unwrap();
+    assert_eq!(config.get_str("log.name"), Some("default.log"));
+    assert_eq!(config.keys().next(), Some("log.name"));
+
+    let error = ParserOptions::new().duplicate_policy(DuplicatePolicy::Error);
+    assert!(matches!(
+        parse_config_with(INPUT, &error),
+        Err(ParseError::DuplicateKey { first_line: 1, line_number: 5, .. })
+    ));
+}
+
+#[test]
+fn test_flatten_config_follows_file_order() {
+    let config = parse_config(INPUT).unwrap();
+
+    let json = flatten_to_nested_json(&config).to_string();
+
+    assert_eq!(
+        json,
+        r#"{"log":{"name":"app.log","file":"/var/log/console.log"},"endpoint":"localhost:3000","db":{"port":"5432"}}"#
+    );
+}
+
+#[test]
+fn test_validate_config_appends_defaults() {
+    let mut config = parse_config("b = 1\na = 2\n").unwrap();
+    let mut schema = BTreeMap::new();
+    schema.insert(
+        "a".to_string(),
+        SchemaEntry { typ: SchemaType::Int, required: true, default: None },
+    );
+    schema.insert(
+        "c".to_string(),
+        SchemaEntry { typ: SchemaType::Bool, required: false, default: Some("true".to_string()) },
+    );
+
+    validate_with_schema(&mut config, &schema).unwrap();
+
+    assert_eq!(config.keys().collect::<Vec<_>>(), vec!["b", "a", "c"]);
+    assert_eq!(config.get_str("c"), Some("true"));
+}
+
+#[test]
+fn test_config_insert_and_remove_keep_order() {
+    let mut config = parse_config("a = 1\nb = 2\nc = 3\n").unwrap();
+
+    assert_eq!(config.insert("a", "10"), Some("1".to_string()));
+    config.insert("d", "4");
+    assert_eq!(config.remove("b"), Some("2".to_string()));
+
+    let pairs: Vec<_> = config.iter().map(|(k, v)| (k.as_str(), v.as_str())).collect();
+    assert_eq!(pairs, vec![("a", "10"), ("c", "3"), ("d", "4")]);
+}
@@ -1,5 +1,11 @@
 use std::collections::BTreeMap;
-use confparser::converter::flatten_to_nested_json;
+use confparser::converter::{
+    flatten_to_nested_json, flatten_to_typed_json, nested_json_to_flat, nested_json_to_flat_with, try_flatten_to_nested_json,
+    try_flatten_to_nested_json_with,
+};
+use confparser::{
+    ArrayPolicy, ConflictStrategy, ConverterOptions, NullPolicy, ParseError, parse_config, parse_schema_str,
+};
 use serde_json::json;
 
 #[test]
@@ -45,3 +51,245 @@ fn test_nested_with_multiple_levels() {
 
     assert_eq!(nested, expected);
 }
+
+#[test]
+fn test_nested_json_to_flat_basic() {
+    let nested = json!({
+        "log": { "file": "/var/log/console.log", "rotate": 7, "compress": true },
+        "net": { "ipv4": { "tcp_rmem": [4096, 87380, 6291456] } },
+        "unused": null
+    });
+
+    let flat = nested_json_to_flat(&nested).unwrap();
+
+    let pairs: Vec<_> = flat.iter().map(|(k, v)| (k.as_str(), v.as_str())).collect();
+    assert_eq!(
+        pairs,
+        vec![
+            ("log.compress", "true"),
+            ("log.file", "/var/log/console.log"),
+            ("log.rotate", "7"),
+            ("net.ipv4.tcp_rmem", "4096 87380 6291456"),
+        ]
+    );
+
+    // sysctl と同じ `1` / `0` にもできる
+    let numeric = nested_json_to_flat_with(&nested, &ConverterOptions::new().bools_as_words(false)).unwrap();
+    assert_eq!(numeric.get("log.compress").map(String::as_str), Some("1"));
+}
+
+#[test]
+fn test_nested_json_to_flat_roundtrip() {
+    let mut flat_map = BTreeMap::new();
+    flat_map.insert("endpoint".to_string(), "localhost:3000".to_string());
+    flat_map.insert("net.ipv4.conf.eth0/100.rp_filter".to_string(), "2".to_string());
+
+    let nested = flatten_to_nested_json(&flat_map);
+
+    assert_eq!(nested["net"]["ipv4"]["conf"]["eth0.100"]["rp_filter"], "2");
+    assert_eq!(nested_json_to_flat(&nested).unwrap(), flat_map);
+}
+
+#[test]
+fn test_nested_json_to_flat_indexed_arrays() {
+    let nested = json!({ "servers": [{ "host": "a" }, { "host": "b" }], "ports": [80, 443] });
+    let options = ConverterOptions::new().arrays(ArrayPolicy::Indexed).bools_as_words(true);
+
+    let flat = nested_json_to_flat_with(&nested, &options).unwrap();
+
+    assert_eq!(flat.get("servers.0.host"), Some(&"a".to_string()));
+    assert_eq!(flat.get("servers.1.host"), Some(&"b".to_string()));
+    assert_eq!(flat.get("ports.1"), Some(&"443".to_string()));
+
+    let joined = nested_json_to_flat(&nested);
+    assert!(matches!(joined, Err(ParseError::InvalidValue { key, .. }) if key == "servers"));
+}
+
+#[test]
+fn test_nested_json_to_flat_null_policies() {
+    let nested = json!({ "a": null, "b": "x" });
+
+    let empty = nested_json_to_flat_with(&nested, &ConverterOptions::new().nulls(NullPolicy::Empty)).unwrap();
+    assert_eq!(empty.get("a"), Some(&String::new()));
+
+    let error = nested_json_to_flat_with(&nested, &ConverterOptions::new().nulls(NullPolicy::Error));
+    assert!(matches!(error, Err(ParseError::InvalidValue { key, .. }) if key == "a"));
+
+    assert!(nested_json_to_flat(&json!([1, 2])).is_err());
+}
+
+#[test]
+fn test_try_flatten_reports_conflicting_keys() {
+    let mut flat_map = BTreeMap::new();
+    flat_map.insert("log".to_string(), "on".to_string());
+    flat_map.insert("log.file".to_string(), "x".to_string());
+
+    let result = try_flatten_to_nested_json(&flat_map);
+
+    assert!(matches!(
+        result,
+        Err(ParseError::KeyConflict { key, conflicting_key }) if key == "log.file" && conflicting_key == "log"
+    ));
+}
+
+#[test]
+fn test_try_flatten_reports_subtree_before_scalar() {
+    let config = parse_config("log.file.path = x\nlog.file = on\n").unwrap();
+
+    let result = try_flatten_to_nested_json(&config);
+
+    assert!(matches!(
+        result,
+        Err(ParseError::KeyConflict { key, conflicting_key }) if key == "log.file" && conflicting_key == "log.file.path"
+    ));
+}
+
+#[test]
+fn test_conflicts_with_reserved_key() {
+    let options = ConverterOptions::new().conflicts(ConflictStrategy::ReservedKey("_value".to_string()));
+    let scalar_first = parse_config("log = on\nlog.file = x\n").unwrap();
+    let subtree_first = parse_config("log.file = x\nlog = on\n").unwrap();
+
+    let expected = json!({ "log": { "_value": "on", "file": "x" } });
+    assert_eq!(try_flatten_to_nested_json_with(&scalar_first, &options).unwrap(), expected);
+    assert_eq!(try_flatten_to_nested_json_with(&subtree_first, &options).unwrap(), expected);
+}
+
+#[test]
+fn test_flatten_to_nested_json_does_not_panic_on_conflict() {
+    let scalar_first = parse_config("log = on\nlog.file = x\n").unwrap();
+    let subtree_first = parse_config("log.file = x\nlog = on\n").unwrap();
+
+    assert_eq!(flatten_to_nested_json(&scalar_first), json!({ "log": { "file": "x" } }));
+    assert_eq!(flatten_to_nested_json(&subtree_first), json!({ "log": "on" }));
+}
+
+const TYPED_SCHEMA: &str = r#"
+vm.swappiness = int
+vm.overcommit_ratio = float
+debug = bool
+log.type = ["file", "console"]
+net.ipv4.tcp_rmem = int[]
+"#;
+
+#[test]
+fn test_flatten_to_typed_json() {
+    let schema = parse_schema_str(TYPED_SCHEMA).unwrap();
+    let config = parse_config(
+        "vm.swappiness = 60\nvm.overcommit_ratio = 0.5\ndebug = TRUE\nlog.type = file\nnet.ipv4.tcp_rmem = 4096 87380 6291456\nendpoint = 3000\n",
+    )
+    .unwrap();
+
+    let typed = flatten_to_typed_json(&config, &schema).unwrap();
+
+    assert_eq!(
+        typed,
+        json!({
+            "vm": { "swappiness": 60, "overcommit_ratio": 0.5 },
+            "debug": true,
+            "log": { "type": "file" },
+            "net": { "ipv4": { "tcp_rmem": [4096, 87380, 6291456] } },
+            "endpoint": "3000"
+        })
+    );
+}
+
+#[test]
+fn test_flatten_to_typed_json_rejects_mistyped_values() {
+    let schema = parse_schema_str(TYPED_SCHEMA).unwrap();
+    let config = parse_config("net.ipv4.tcp_rmem = 4096 lots\n").unwrap();
+
+    let result = flatten_to_typed_json(&config, &schema);
+
+    assert!(matches!(result, Err(ParseError::InvalidValue { key, .. }) if key == "net.ipv4.tcp_rmem"));
+}
+
+#[test]
+fn test_custom_separator() {
+    let mut flat_map = BTreeMap::new();
+    flat_map.insert("db__mysql__user".to_string(), "root".to_string());
+    flat_map.insert("db__log.file".to_string(), "/var/log/db.log".to_string());
+    let options = ConverterOptions::new().separator("__");
+
+    let nested = try_flatten_to_nested_json_with(&flat_map, &options).unwrap();
+
+    assert_eq!(nested, json!({ "db": { "mysql": { "user": "root" }, "log.file": "/var/log/db.log" } }));
+    assert_eq!(nested_json_to_flat_with(&nested, &options).unwrap(), flat_map);
+}
+
+#[test]
+fn test_numeric_segments_as_array_indices() {
+    let config = parse_config("servers.0.host = a\nservers.1.host = b\nservers.1.port = 8080\nports.2 = 443\nlevels.01 = x\n").unwrap();
+    let options = ConverterOptions::new().array_indices(true);
+
+    let nested = try_flatten_to_nested_json_with(&config, &options).unwrap();
+
+    assert_eq!(
+        nested,
+        json!({
+            "servers": [{ "host": "a" }, { "host": "b", "port": "8080" }],
+            "ports": { "2": "443" },
+            "levels": { "01": "x" }
+        })
+    );
+
+    // 添字のキーに戻すと元のマップになる
+    let back = nested_json_to_flat_with(&nested, &ConverterOptions::new().arrays(ArrayPolicy::Indexed)).unwrap();
+    assert_eq!(back, config.to_btree_map());
+}
+
+#[test]
+fn test_root_with_index_keys_stays_an_object() {
+    let config = parse_config("0.host = a\n1.host = b\n").unwrap();
+    let options = ConverterOptions::new().array_indices(true);
+
+    let nested = try_flatten_to_nested_json_with(&config, &options).unwrap();
+
+    assert_eq!(nested, json!({ "0": { "host": "a" }, "1": { "host": "b" } }));
+}
+
+#[test]
+fn test_array_indices_off_by_default() {
+    let mut flat_map = BTreeMap::new();
+    flat_map.insert("servers.0.host".to_string(), "a".to_string());
+
+    assert_eq!(flatten_to_nested_json(&flat_map), json!({ "servers": { "0": { "host": "a" } } }));
+}
+
+#[test]
+fn test_escaped_separator_inside_segment() {
+    let mut flat_map = BTreeMap::new();
+    flat_map.insert(r"hosts/example\/com/port".to_string(), "443".to_string());
+    flat_map.insert(r"paths/c:\\tmp".to_string(), "1".to_string());
+    let options = ConverterOptions::new().separator("/").escape('\\');
+
+    let nested = try_flatten_to_nested_json_with(&flat_map, &options).unwrap();
+
+    assert_eq!(nested, json!({ "hosts": { "example/com": { "port": "443" } }, "paths": { r"c:\tmp": "1" } }));
+    assert_eq!(nested_json_to_flat_with(&nested, &options).unwrap(), flat_map);
+}
+
+#[test]
+fn test_unescapable_separator_is_an_error() {
+    let nested = json!({ "hosts": { "example/com": "443" } });
+
+    let result = nested_json_to_flat_with(&nested, &ConverterOptions::new().separator("/"));
+
+    assert!(matches!(result, Err(ParseError::InvalidValue { key, .. }) if key == "hosts/example/com"));
+}
+
+#[test]
+fn test_huge_array_index_stays_an_object() {
+    let config = parse_config("servers.18446744073709551615.host = a\nhosts.4000000000 = b\nhosts.0 = c\n").unwrap();
+    let options = ConverterOptions::new().array_indices(true);
+
+    let nested = try_flatten_to_nested_json_with(&config, &options).unwrap();
+
+    assert_eq!(
+        nested,
+        json!({
+            "servers": { "18446744073709551615": { "host": "a" } },
+            "hosts": { "4000000000": "b", "0": "c" }
+        })
+    );
+}
@@ -0,0 +1,107 @@
+use std::collections::BTreeMap;
+use confparser::{ParseError, from_map, from_str, parse_config, parse_str};
+use serde::Deserialize;
+
+#[derive(Debug, Deserialize, PartialEq)]
+struct AppConfig {
+    endpoint: String,
+    log: LogConfig,
+    db: DbConfig,
+    #[serde(default)]
+    optional: Option<u32>,
+}
+
+#[derive(Debug, Deserialize, PartialEq)]
+struct LogConfig {
+    file: String,
+    level: Level,
+}
+
+#[derive(Debug, Deserialize, PartialEq)]
+#[serde(rename_all = "lowercase")]
+enum Level {
+    Debug,
+    Info,
+}
+
+#[derive(Debug, Deserialize, PartialEq)]
+struct DbConfig {
+    port: u16,
+    debug: bool,
+    timeout: f64,
+}
+
+const INPUT: &str = "\
+endpoint = localhost:3000
+log.file = /var/log/console.log
+log.level = info
+db.port = 5432
+db.debug = yes
+db.timeout = 2.5
+";
+
+#[test]
+fn test_from_str_into_nested_struct() {
+    let config: AppConfig = from_str(INPUT).unwrap();
+
+    assert_eq!(
+        config,
+        AppConfig {
+            endpoint: "localhost:3000".to_string(),
+            log: LogConfig {
+                file: "/var/log/console.log".to_string(),
+                level: Level::Info,
+            },
+            db: DbConfig {
+                port: 5432,
+                debug: true,
+                timeout: 2.5,
+            },
+            optional: None,
+        }
+    );
+}
+
+#[test]
+fn test_from_map_accepts_btree_map_and_config() {
+    let from_btree: AppConfig = from_map(&parse_str(INPUT).unwrap()).unwrap();
+    let from_config: AppConfig = from_map(&parse_config(INPUT).unwrap()).unwrap();
+
+    assert_eq!(from_btree, from_config);
+    assert_eq!(from_btree.log.level, Level::Info);
+}
+
+#[test]
+fn test_sequences_from_whitespace_and_indexed_keys() {
+    #[derive(Debug, Deserialize)]
+    struct Net {
+        tcp_rmem: Vec<u32>,
+        servers: Vec<String>,
+    }
+
+    let net: Net = from_str("tcp_rmem = 4096 87380 6291456\nservers.1 = b\nservers.0 = a\nservers.10 = c\n").unwrap();
+
+    assert_eq!(net.tcp_rmem, vec![4096, 87380, 6291456]);
+    assert_eq!(net.servers, vec!["a", "b", "c"]);
+}
+
+#[test]
+fn test_invalid_value_names_key() {
+    let input = INPUT.replace("db.port = 5432", "db.port = http");
+
+    let error = from_str::<AppConfig>(&input).unwrap_err();
+
+    assert!(matches!(&error, ParseError::InvalidValue { key, value, .. } if key == "db.port" && value == "http"));
+}
+
+#[test]
+fn test_missing_field_and_conflicting_keys() {
+    let missing = from_str::<AppConfig>("endpoint = x\n").unwrap_err();
+    assert!(matches!(&missing, ParseError::Serde(msg) if msg.contains("missing field")));
+
+    let mut conflicting = BTreeMap::new();
+    conflicting.insert("log".to_string(), "on".to_string());
+    conflicting.insert("log.file".to_string(), "x".to_string());
+    let error = from_map::<BTreeMap<String, String>, _>(&conflicting).unwrap_err();
+    assert!(matches!(&error, ParseError::Serde(msg) if msg.contains("'log.file'")));
+}
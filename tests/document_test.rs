@@ -0,0 +1,187 @@
+use std::fs;
+use confparser::{ConfDocument, DuplicatePolicy, ParseError, ParserOptions, parse_str, parse_str_with};
+use confparser::document::LineKind;
+
+#[test]
+fn test_document_roundtrip_sample_file() {
+    let input = std::fs::read_to_string("conf/sysctl.conf").unwrap();
+    let doc = ConfDocument::parse(&input);
+
+    assert_eq!(doc.to_string(), input);
+}
+
+#[test]
+fn test_document_roundtrip_trivia() {
+    let input = "# head\r\n\n  net.ipv4.ip_forward   =    1   \t# inline\n\tvm.swappiness\t=\t60\nfs.file-max =\ninvalid line";
+    let doc = ConfDocument::parse(input);
+
+    assert_eq!(doc.to_string(), input);
+    assert_eq!(doc.lines().len(), 6);
+    assert!(matches!(doc.lines()[5].kind, LineKind::Invalid(_)));
+}
+
+#[test]
+fn test_document_entry_tokens() {
+    let doc = ConfDocument::parse("  net.core.somaxconn = 1024   # comment\n");
+    let (line_number, entry) = doc.entries().next().unwrap();
+
+    assert_eq!(line_number, 1);
+    assert_eq!(entry.leading, "  ");
+    assert_eq!(entry.key, "net.core.somaxconn");
+    assert_eq!(entry.value, "1024");
+    assert_eq!(entry.trailing, "   # comment");
+}
+
+#[test]
+fn test_document_keeps_duplicate_assignments() {
+    let input = "net.ipv4.ip_forward = 1\nnet.ipv4.ip_forward = false\n";
+    let doc = ConfDocument::parse(input);
+
+    assert_eq!(doc.entries().count(), 2);
+    assert_eq!(doc.to_map().unwrap(), parse_str(input).unwrap());
+}
+
+#[test]
+fn test_document_set_updates_only_value() {
+    let input = "# tuning\nvm.swappiness\t=\t60   # default\nnet.core.somaxconn = 1024\n";
+    let mut doc = ConfDocument::parse(input);
+
+    doc.set("vm.swappiness", "10").unwrap();
+    doc.set("net.core.somaxconn", "4096").unwrap();
+
+    assert_eq!(
+        doc.to_string(),
+        "# tuning\nvm.swappiness\t=\t10   # default\nnet.core.somaxconn = 4096\n"
+    );
+}
+
+#[test]
+fn test_document_set_appends_missing_key() {
+    let mut doc = ConfDocument::parse("kernel.shmall = 2097152");
+
+    doc.set("vm.swappiness", "10").unwrap();
+
+    assert_eq!(doc.to_string(), "kernel.shmall = 2097152\nvm.swappiness = 10\n");
+    assert_eq!(doc.get("vm.swappiness"), Some("10"));
+}
+
+#[test]
+fn test_document_set_rejects_inline_comment() {
+    let mut doc = ConfDocument::parse("vm.swappiness = 60\n");
+
+    assert!(doc.set("vm.swappiness", "10 # oops").is_err());
+    assert_eq!(doc.get("vm.swappiness"), Some("60"));
+}
+
+#[test]
+fn test_document_remove_insert_after_and_comment_out() {
+    let input = "# net\nnet.ipv4.ip_forward = 1\nvm.swappiness = 60\nkernel.shmall = 2097152\n";
+    let mut doc = ConfDocument::parse(input);
+
+    assert!(doc.remove("kernel.shmall"));
+    assert!(!doc.remove("kernel.shmall"));
+    assert!(doc.insert_after("net.ipv4.ip_forward", "net.core.somaxconn", "1024").unwrap());
+    assert!(!doc.insert_after("missing.key", "a", "b").unwrap());
+    assert!(doc.comment_out("vm.swappiness"));
+
+    assert_eq!(
+        doc.to_string(),
+        "# net\nnet.ipv4.ip_forward = 1\nnet.core.somaxconn = 1024\n# vm.swappiness = 60\n"
+    );
+    assert_eq!(doc.get("vm.swappiness"), None);
+}
+
+#[test]
+fn test_document_dash_prefix_roundtrip() {
+    let input = "  -net.ipv4.conf.all.rp_filter = 1\n";
+    let mut doc = ConfDocument::parse(input);
+
+    let (_, entry) = doc.entries().next().unwrap();
+    assert!(entry.ignore_failure);
+    assert_eq!(entry.key, "net.ipv4.conf.all.rp_filter");
+    assert_eq!(doc.to_string(), input);
+
+    doc.set("net.ipv4.conf.all.rp_filter", "2").unwrap();
+    assert_eq!(doc.to_string(), "  -net.ipv4.conf.all.rp_filter = 2\n");
+}
+
+#[test]
+fn test_document_follows_duplicate_policy() {
+    let input = "a = 1\nb = x\na = 2\n";
+    let first_wins = ParserOptions::new().duplicate_policy(DuplicatePolicy::FirstWins);
+
+    let mut doc = ConfDocument::parse_with(input, &first_wins);
+    assert_eq!(doc.get("a"), Some("1"));
+    assert_eq!(doc.to_map().unwrap(), parse_str_with(input, &first_wins).unwrap());
+    assert_eq!(doc.to_config().unwrap().get_str("a"), Some("1"));
+
+    // 採用される最初の行だけが書き換わる
+    doc.set("a", "10").unwrap();
+    assert_eq!(doc.get("a"), Some("10"));
+    assert_eq!(doc.to_string(), "a = 10\nb = x\na = 2\n");
+
+    let error = ParserOptions::new().duplicate_policy(DuplicatePolicy::Error);
+    let doc = ConfDocument::parse_with(input, &error);
+    assert!(matches!(doc.to_map(), Err(ParseError::DuplicateKey { first_line: 1, line_number: 3, .. })));
+    assert!(matches!(doc.to_config(), Err(ParseError::DuplicateKey { .. })));
+}
+
+#[test]
+fn test_set_reports_physical_line_number() {
+    let options = ParserOptions::new().line_continuation(true).max_value_length(Some(4));
+    let mut doc = ConfDocument::parse_with("a = 1 \\\n  2\nb = x\n", &options);
+
+    // 継続行を含む1行目の後なので、b は3行目
+    assert!(matches!(doc.set("b", "too long"), Err(ParseError::ValueTooLong { line_number: 3, .. })));
+    assert!(matches!(doc.set("c", "too long"), Err(ParseError::ValueTooLong { line_number: 4, .. })));
+    assert_eq!(doc.to_string(), "a = 1 \\\n  2\nb = x\n");
+}
+
+#[test]
+fn test_from_file_with_options() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("99-tuning.conf");
+    fs::write(&path, "net.ipv4.tcp_rmem = 4096 \\\n    87380\n").unwrap();
+
+    let doc = ConfDocument::from_file_with(&path, &ParserOptions::new().line_continuation(true)).unwrap();
+
+    assert_eq!(doc.get("net.ipv4.tcp_rmem"), Some("4096 87380"));
+    assert!(ConfDocument::from_file(&path).unwrap().to_map().is_err());
+}
+
+#[test]
+fn test_write_to_file_replaces_the_file() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("99-tuning.conf");
+    fs::write(&path, "# tuning\nvm.swappiness = 60\n").unwrap();
+
+    let mut doc = ConfDocument::from_file(&path).unwrap();
+    doc.set("vm.swappiness", "10").unwrap();
+    doc.write_to_file(&path).unwrap();
+
+    assert_eq!(fs::read_to_string(&path).unwrap(), "# tuning\nvm.swappiness = 10\n");
+    // 一時ファイルは残らない
+    let names: Vec<_> = fs::read_dir(dir.path()).unwrap().map(|e| e.unwrap().file_name()).collect();
+    assert_eq!(names, ["99-tuning.conf"]);
+}
+
+#[cfg(unix)]
+#[test]
+fn test_write_to_file_keeps_permissions_and_symlinks() {
+    use std::os::unix::fs::{PermissionsExt, symlink};
+
+    let dir = tempfile::tempdir().unwrap();
+    let target = dir.path().join("sysctl.conf");
+    let link = dir.path().join("99-sysctl.conf");
+    fs::write(&target, "vm.swappiness = 60\n").unwrap();
+    fs::set_permissions(&target, fs::Permissions::from_mode(0o600)).unwrap();
+    symlink(&target, &link).unwrap();
+
+    let mut doc = ConfDocument::from_file(&link).unwrap();
+    doc.set("vm.swappiness", "10").unwrap();
+    doc.write_to_file(&link).unwrap();
+
+    assert!(fs::symlink_metadata(&link).unwrap().file_type().is_symlink());
+    assert_eq!(fs::read_to_string(&target).unwrap(), "vm.swappiness = 10\n");
+    assert_eq!(fs::metadata(&target).unwrap().permissions().mode() & 0o777, 0o600);
+}
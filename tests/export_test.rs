@@ -0,0 +1,157 @@
+use confparser::{
+    EnvOptions, ParseError, SysctlKey, flatten_to_nested_json, flatten_to_typed_json, parse_config, parse_schema_str,
+    to_env, to_ini, to_toml, to_yaml,
+};
+use serde_json::json;
+
+const INPUT: &str = "\
+endpoint = localhost:3000
+net.ipv4.ip_forward = 1
+net.ipv4.tcp_rmem = 4096 87380 6291456
+log.file = /var/log/console.log
+log.name = default log
+";
+
+const SCHEMA: &str = "\
+net.ipv4.ip_forward = int
+net.ipv4.tcp_rmem = int[]
+";
+
+#[test]
+fn test_to_yaml_follows_typed_json() {
+    let schema = parse_schema_str(SCHEMA).unwrap();
+    let typed = flatten_to_typed_json(&parse_config(INPUT).unwrap(), &schema).unwrap();
+
+    let yaml = to_yaml(&typed).unwrap();
+
+    assert_eq!(
+        yaml,
+        "\
+endpoint: localhost:3000
+net:
+  ipv4:
+    ip_forward: 1
+    tcp_rmem:
+    - 4096
+    - 87380
+    - 6291456
+log:
+  file: /var/log/console.log
+  name: default log
+"
+    );
+}
+
+#[test]
+fn test_to_toml_uses_dotted_tables() {
+    let schema = parse_schema_str(SCHEMA).unwrap();
+    let typed = flatten_to_typed_json(&parse_config(INPUT).unwrap(), &schema).unwrap();
+
+    let toml = to_toml(&typed).unwrap();
+
+    assert_eq!(
+        toml,
+        "\
+endpoint = \"localhost:3000\"
+
+[net.ipv4]
+ip_forward = 1
+tcp_rmem = [4096, 87380, 6291456]
+
+[log]
+file = \"/var/log/console.log\"
+name = \"default log\"
+"
+    );
+}
+
+#[test]
+fn test_to_ini_uses_first_segment_as_section() {
+    let nested = flatten_to_nested_json(&parse_config(INPUT).unwrap());
+
+    let ini = to_ini(&nested).unwrap();
+
+    assert_eq!(
+        ini,
+        "\
+endpoint = localhost:3000
+
+[net]
+ipv4.ip_forward = 1
+ipv4.tcp_rmem = 4096 87380 6291456
+
+[log]
+file = /var/log/console.log
+name = default log
+"
+    );
+}
+
+#[test]
+fn test_to_env_with_key_mangling() {
+    let nested = flatten_to_nested_json(&parse_config(INPUT).unwrap());
+
+    let default = to_env(&nested, &EnvOptions::new()).unwrap();
+    assert_eq!(
+        default,
+        "\
+ENDPOINT=localhost:3000
+NET__IPV4__IP_FORWARD=1
+NET__IPV4__TCP_RMEM='4096 87380 6291456'
+LOG__FILE=/var/log/console.log
+LOG__NAME='default log'
+"
+    );
+
+    let custom = to_env(&nested, &EnvOptions::new().prefix("sysctl_").separator("_").uppercase(false)).unwrap();
+    assert!(custom.contains("sysctl_net_ipv4_ip_forward=1\n"));
+}
+
+#[test]
+fn test_exporters_reject_unrepresentable_values() {
+    let nested = json!({ "matrix": [[1, 2], [3]], "motd": "a\nb" });
+
+    assert!(matches!(to_ini(&nested), Err(ParseError::InvalidValue { key, .. }) if key == "matrix"));
+    assert!(matches!(to_env(&json!({ "motd": "a\nb" }), &EnvOptions::new()), Err(ParseError::InvalidValue { .. })));
+    assert!(to_env(&json!("scalar"), &EnvOptions::new()).is_err());
+}
+
+#[test]
+fn test_to_ini_escapes_dotted_segments_and_comment_characters() {
+    let nested = json!({
+        "motd": "a; b # c",
+        "net": { "ipv4": { "conf": { "eth0.100": { "rp_filter": 2 } } } },
+        "log": { "name": " padded " }
+    });
+
+    let ini = to_ini(&nested).unwrap();
+
+    assert_eq!(
+        ini,
+        "\
+motd = \"a; b # c\"
+
+[net]
+ipv4.conf.eth0/100.rp_filter = 2
+
+[log]
+name = \" padded \"
+"
+    );
+
+    // INI のキーは sysctl と同じ正規形なので、元のキーに戻る
+    let key = ini.lines().find(|line| line.starts_with("ipv4")).unwrap().split(" = ").next().unwrap();
+    assert_eq!(SysctlKey::parse(key).segments(), ["ipv4", "conf", "eth0.100", "rp_filter"]);
+}
+
+#[test]
+fn test_exporters_reject_unwritable_segments() {
+    let dotted = json!({ "net": { "ipv4": { "conf": { "eth0.100": { "rp_filter": 2 } } } } });
+    assert!(matches!(
+        to_env(&dotted, &EnvOptions::new()),
+        Err(ParseError::InvalidValue { key, value, .. }) if key == "net.ipv4.conf.eth0/100.rp_filter" && value == "eth0.100"
+    ));
+
+    assert!(matches!(to_ini(&json!({ "eth0.100": { "rp_filter": 2 } })), Err(ParseError::InvalidValue { .. })));
+    assert!(matches!(to_ini(&json!({ "net": { "a/b": 1 } })), Err(ParseError::InvalidValue { .. })));
+}
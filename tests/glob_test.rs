@@ -0,0 +1,64 @@
+use std::collections::BTreeMap;
+use confparser::{KeyPattern, SysctlRules, ConfDocument, parse_str};
+
+const PROC_KEYS: [&str; 4] = [
+    "net.ipv4.conf.all.rp_filter",
+    "net.ipv4.conf.default.rp_filter",
+    "net.ipv4.conf.eth0.rp_filter",
+    "net.ipv4.conf.lo.rp_filter",
+];
+
+#[test]
+fn test_key_pattern_matches_single_segment() {
+    let pattern = KeyPattern::new("net.ipv4.conf.*.rp_filter");
+
+    assert!(pattern.matches("net.ipv4.conf.eth0.rp_filter"));
+    assert!(!pattern.matches("net.ipv4.conf.eth0.sub.rp_filter"));
+    assert!(!pattern.matches("net.ipv4.conf.eth0.accept_redirects"));
+
+    assert!(KeyPattern::new("net.ipv4.conf.eth?.rp_filter").matches("net.ipv4.conf.eth1.rp_filter"));
+    assert!(KeyPattern::new("net.ipv4.conf.[el]*.rp_filter").matches("net.ipv4.conf.lo.rp_filter"));
+    assert!(!KeyPattern::new("net.ipv4.conf.[!el]*.rp_filter").matches("net.ipv4.conf.lo.rp_filter"));
+}
+
+#[test]
+fn test_expand_glob_with_exclusion() {
+    let input = "
+        net.ipv4.conf.*.rp_filter = 2
+        -net.ipv4.conf.lo.rp_filter
+        net.ipv4.conf.all.rp_filter = 1
+    ";
+
+    let rules = SysctlRules::parse_str(input).unwrap();
+    let expanded = rules.expand(PROC_KEYS);
+
+    let expected = BTreeMap::from([
+        ("net.ipv4.conf.all.rp_filter".to_string(), "1".to_string()),
+        ("net.ipv4.conf.default.rp_filter".to_string(), "2".to_string()),
+        ("net.ipv4.conf.eth0.rp_filter".to_string(), "2".to_string()),
+    ]);
+    assert_eq!(expanded, expected);
+}
+
+#[test]
+fn test_later_glob_wins() {
+    let input = "
+        net.ipv4.conf.*.rp_filter = 2
+        net.ipv4.conf.eth*.rp_filter = 0
+    ";
+
+    let expanded = SysctlRules::parse_str(input).unwrap().expand(PROC_KEYS);
+
+    assert_eq!(expanded.get("net.ipv4.conf.eth0.rp_filter"), Some(&"0".to_string()));
+    assert_eq!(expanded.get("net.ipv4.conf.lo.rp_filter"), Some(&"2".to_string()));
+}
+
+#[test]
+fn test_exclusion_line_is_lossless_and_ignored_by_parse_str() {
+    let input = "net.ipv4.conf.*.rp_filter = 2\n  -net.ipv4.conf.lo.rp_filter   # keep loopback\n";
+
+    assert_eq!(ConfDocument::parse(input).to_string(), input);
+
+    let map = parse_str(input).unwrap();
+    assert_eq!(map.len(), 1);
+}
@@ -0,0 +1,130 @@
+use std::collections::BTreeMap;
+use confparser::{
+    EnvOptions, ParseError, flatten_to_nested_json, from_env_vars, from_json, from_toml, from_yaml,
+    parse_schema_str, parse_str, to_env, validate_with_schema,
+};
+
+const CONF: &str = "\
+endpoint = localhost:3000
+net.ipv4.ip_forward = 1
+net.ipv4.tcp_rmem = 4096 87380 6291456
+log.name = default log
+";
+
+#[test]
+fn test_from_json_matches_parse_str() {
+    let json = r#"{
+        "endpoint": "localhost:3000",
+        "net": { "ipv4": { "ip_forward": 1, "tcp_rmem": [4096, 87380, 6291456] } },
+        "log": { "name": "default log", "file": null }
+    }"#;
+
+    assert_eq!(from_json(json).unwrap(), parse_str(CONF).unwrap());
+}
+
+#[test]
+fn test_from_yaml_matches_parse_str() {
+    let yaml = "\
+endpoint: localhost:3000
+net:
+  ipv4:
+    ip_forward: 1
+    tcp_rmem: [4096, 87380, 6291456]
+log:
+  name: default log
+";
+
+    assert_eq!(from_yaml(yaml).unwrap(), parse_str(CONF).unwrap());
+}
+
+#[test]
+fn test_from_toml_matches_parse_str() {
+    let toml = "\
+endpoint = \"localhost:3000\"
+
+[net.ipv4]
+ip_forward = 1
+tcp_rmem = [4096, 87380, 6291456]
+
+[log]
+name = \"default log\"
+";
+
+    assert_eq!(from_toml(toml).unwrap(), parse_str(CONF).unwrap());
+}
+
+#[test]
+fn test_from_env_vars() {
+    let vars = [
+        ("SYSCTL_NET__IPV4__IP_FORWARD", "1"),
+        ("SYSCTL_LOG__NAME", "default log"),
+        ("SYSCTL_", "ignored"),
+        ("PATH", "/usr/bin"),
+    ];
+    let flat = from_env_vars(vars, &EnvOptions::new().prefix("SYSCTL_"));
+
+    let expected: BTreeMap<String, String> = [("net.ipv4.ip_forward", "1"), ("log.name", "default log")]
+        .into_iter()
+        .map(|(k, v)| (k.to_string(), v.to_string()))
+        .collect();
+    assert_eq!(flat, expected);
+
+    let raw = from_env_vars(
+        [("app_Kernel_panic", "10")],
+        &EnvOptions::new().prefix("app_").separator("_").uppercase(false),
+    );
+    assert_eq!(raw.get("Kernel.panic").map(String::as_str), Some("10"));
+}
+
+#[test]
+fn test_imported_map_goes_through_schema_validation() {
+    let schema = parse_schema_str("net.ipv4.ip_forward = bool\nkernel.panic = int(default=0)\n").unwrap();
+
+    let mut config = from_yaml("net:\n  ipv4:\n    ip_forward: true\n").unwrap();
+    validate_with_schema(&mut config, &schema).unwrap();
+    assert_eq!(config.get("kernel.panic").map(String::as_str), Some("0"));
+
+    let mut from_json_bool = from_json(r#"{ "net": { "ipv4": { "ip_forward": false } } }"#).unwrap();
+    assert_eq!(from_json_bool.get("net.ipv4.ip_forward").map(String::as_str), Some("false"));
+    assert!(validate_with_schema(&mut from_json_bool, &schema).is_ok());
+
+    let mut from_toml_bool = from_toml("[net.ipv4]\nip_forward = true\n").unwrap();
+    assert!(validate_with_schema(&mut from_toml_bool, &schema).is_ok());
+
+    let mut invalid = from_json(r#"{ "net": { "ipv4": { "ip_forward": "yes" } } }"#).unwrap();
+    assert!(validate_with_schema(&mut invalid, &schema).is_err());
+}
+
+#[test]
+fn test_import_errors() {
+    assert!(matches!(from_json("{ not json"), Err(ParseError::Serde(_))));
+    assert!(matches!(from_yaml("- a\n- b\n"), Err(ParseError::InvalidValue { .. })));
+    assert!(matches!(from_toml("key = "), Err(ParseError::Serde(_))));
+}
+
+#[test]
+fn test_from_toml_rejects_non_finite_floats() {
+    assert!(matches!(
+        from_toml("[vm]\nratio = nan\n"),
+        Err(ParseError::InvalidValue { key, .. }) if key == "vm.ratio"
+    ));
+    assert!(matches!(
+        from_toml("[net.ipv4]\nlimits = [1.5, inf]\n"),
+        Err(ParseError::InvalidValue { key, .. }) if key == "net.ipv4.limits.1"
+    ));
+    assert_eq!(from_toml("[vm]\nratio = 1.5\n").unwrap().get("vm.ratio").map(String::as_str), Some("1.5"));
+}
+
+#[test]
+fn test_env_export_round_trips_with_default_options() {
+    let nested = flatten_to_nested_json(&parse_str(CONF).unwrap());
+    let env = to_env(&nested, &EnvOptions::new().prefix("SYSCTL_")).unwrap();
+
+    // シェルが読み込んだ後の (名前, 値) にする
+    let vars = env.lines().map(|line| {
+        let (name, value) = line.split_once('=').unwrap();
+        (name.to_string(), value.trim_matches('\'').to_string())
+    });
+
+    assert_eq!(from_env_vars(vars, &EnvOptions::new().prefix("SYSCTL_")), parse_str(CONF).unwrap());
+}
@@ -0,0 +1,87 @@
+use confparser::{SysctlKey, SysctlRules, parse_str, flatten_to_nested_json};
+use serde_json::json;
+
+#[test]
+fn test_slash_and_dot_forms_are_equal() {
+    let dotted = SysctlKey::parse("net.ipv4.ip_forward");
+    let slashed = SysctlKey::parse("net/ipv4/ip_forward");
+
+    assert_eq!(dotted, slashed);
+    assert_eq!(slashed.segments(), ["net", "ipv4", "ip_forward"]);
+    assert_eq!(slashed.to_string(), "net.ipv4.ip_forward");
+}
+
+#[test]
+fn test_dot_inside_slash_segment_is_kept() {
+    let key = SysctlKey::parse("net/ipv4/conf/eth0.100/rp_filter");
+
+    assert_eq!(key.segments(), ["net", "ipv4", "conf", "eth0.100", "rp_filter"]);
+    assert_eq!(key.to_dotted(), "net.ipv4.conf.eth0/100.rp_filter");
+    assert_eq!(key.to_slashed(), "net/ipv4/conf/eth0.100/rp_filter");
+    assert_eq!(SysctlKey::parse("net.ipv4.conf.eth0/100.rp_filter"), key);
+}
+
+#[test]
+fn test_parse_str_merges_slash_and_dot_keys() {
+    let input = "
+        net.ipv4.ip_forward = 1
+        net/ipv4/ip_forward = 0
+    ";
+
+    let result = parse_str(input).unwrap();
+
+    assert_eq!(result.len(), 1);
+    assert_eq!(result.get("net.ipv4.ip_forward"), Some(&"0".to_string()));
+}
+
+#[test]
+fn test_nested_json_keeps_dotted_interface_name() {
+    let flat = parse_str("net/ipv4/conf/eth0.100/rp_filter = 1").unwrap();
+
+    let nested = flatten_to_nested_json(&flat);
+
+    assert_eq!(nested, json!({
+        "net": { "ipv4": { "conf": { "eth0.100": { "rp_filter": "1" } } } }
+    }));
+}
+
+#[test]
+fn test_glob_expands_against_slash_keys() {
+    let rules = SysctlRules::parse_str("net.ipv4.conf.*.rp_filter = 2").unwrap();
+
+    let expanded = rules.expand(["net/ipv4/conf/eth0.100/rp_filter"]);
+
+    assert_eq!(expanded.get("net.ipv4.conf.eth0/100.rp_filter"), Some(&"2".to_string()));
+}
+
+#[test]
+fn test_dotted_form_round_trips() {
+    let keys: [&[&str]; 7] = [
+        &["net", "ipv4", "ip_forward"],
+        &["net", "ipv4", "conf", "eth0.100", "rp_filter"],
+        &["eth0.100", "x"],
+        &["a.b"],
+        &["example.com", "port"],
+        &["", "a.b"],
+        &[".", ""],
+    ];
+
+    for segments in keys {
+        let key = SysctlKey::from_segments(segments.iter().copied());
+        assert_eq!(SysctlKey::parse(&key.to_dotted()), key, "{:?}", segments);
+    }
+    assert_eq!(SysctlKey::from_segments(["eth0.100", "x"]).to_dotted(), "/eth0.100/x");
+    assert!(!SysctlKey::from_segments(["a/b"]).is_representable());
+}
+
+#[test]
+fn test_dotted_first_segment_survives_conversion() {
+    let flat = confparser::nested_json_to_flat(&json!({ "example.com": { "port": 1 } })).unwrap();
+    assert_eq!(flat.get("/example.com/port"), Some(&"1".to_string()));
+
+    let reparsed = parse_str("/example.com/port = 1").unwrap();
+    assert_eq!(reparsed, flat);
+    assert_eq!(flatten_to_nested_json(&flat), json!({ "example.com": { "port": "1" } }));
+
+    assert!(confparser::nested_json_to_flat(&json!({ "a/b": 1 })).is_err());
+}
@@ -0,0 +1,150 @@
+use std::fs;
+use std::path::Path;
+use confparser::{ParseError, ParserOptions, SysctlLoader};
+
+fn write(root: &Path, rel: &str, content: &str) {
+    let path = root.join(rel);
+    fs::create_dir_all(path.parent().unwrap()).unwrap();
+    fs::write(path, content).unwrap();
+}
+
+#[test]
+fn test_files_are_ordered_by_name_and_later_wins() {
+    let root = tempfile::tempdir().unwrap();
+    write(root.path(), "usr/lib/sysctl.d/50-default.conf", "vm.swappiness = 60\nkernel.pid_max = 4194304\n");
+    write(root.path(), "etc/sysctl.d/99-tuning.conf", "vm.swappiness = 10\n");
+    write(root.path(), "run/sysctl.d/10-early.conf", "vm.swappiness = 30\n");
+    write(root.path(), "etc/sysctl.d/README", "not a config\n");
+
+    let loaded = SysctlLoader::with_root(root.path()).load().unwrap();
+
+    let names: Vec<_> = loaded.files.iter().map(|p| p.file_name().unwrap().to_str().unwrap()).collect();
+    assert_eq!(names, ["10-early.conf", "50-default.conf", "99-tuning.conf"]);
+
+    let map = loaded.to_map();
+    assert_eq!(map.get("vm.swappiness"), Some(&"10".to_string()));
+    assert_eq!(map.get("kernel.pid_max"), Some(&"4194304".to_string()));
+
+    assert_eq!(loaded.source_of("vm.swappiness"), Some(root.path().join("etc/sysctl.d/99-tuning.conf").as_path()));
+    assert_eq!(loaded.values.get("kernel.pid_max").unwrap().span.line_number, 2);
+}
+
+#[test]
+fn test_same_name_in_higher_priority_dir_masks_lower() {
+    let root = tempfile::tempdir().unwrap();
+    write(root.path(), "usr/lib/sysctl.d/50-default.conf", "vm.swappiness = 60\nkernel.pid_max = 4194304\n");
+    write(root.path(), "etc/sysctl.d/50-default.conf", "vm.swappiness = 20\n");
+
+    let loaded = SysctlLoader::with_root(root.path()).load().unwrap();
+
+    assert_eq!(loaded.files, [root.path().join("etc/sysctl.d/50-default.conf")]);
+    assert_eq!(loaded.to_map().get("kernel.pid_max"), None);
+}
+
+#[cfg(unix)]
+#[test]
+fn test_dev_null_symlink_masks_file() {
+    let root = tempfile::tempdir().unwrap();
+    write(root.path(), "usr/lib/sysctl.d/50-default.conf", "vm.swappiness = 60\n");
+    fs::create_dir_all(root.path().join("etc/sysctl.d")).unwrap();
+    std::os::unix::fs::symlink("/dev/null", root.path().join("etc/sysctl.d/50-default.conf")).unwrap();
+
+    let loaded = SysctlLoader::with_root(root.path()).load().unwrap();
+
+    assert!(loaded.files.is_empty());
+    assert!(loaded.values.is_empty());
+}
+
+#[test]
+fn test_sysctl_conf_is_applied_last() {
+    let root = tempfile::tempdir().unwrap();
+    write(root.path(), "etc/sysctl.d/99-tuning.conf", "vm.swappiness = 10\n");
+    write(root.path(), "etc/sysctl.conf", "vm.swappiness = 1\n");
+
+    let loaded = SysctlLoader::with_root(root.path()).load().unwrap();
+
+    assert_eq!(loaded.to_map().get("vm.swappiness"), Some(&"1".to_string()));
+    assert_eq!(loaded.source_of("vm.swappiness"), Some(root.path().join("etc/sysctl.conf").as_path()));
+}
+
+#[test]
+fn test_parse_error_reports_file() {
+    let root = tempfile::tempdir().unwrap();
+    write(root.path(), "etc/sysctl.d/10-bad.conf", "vm.swappiness = 10\ninvalid line\n");
+
+    let result = SysctlLoader::with_root(root.path()).load();
+
+    match result {
+        Err(ParseError::InFile { path, error }) => {
+            assert!(path.ends_with("10-bad.conf"));
+            assert!(matches!(*error, ParseError::InvalidLine { line_number: 2, .. }));
+        }
+        other => panic!("unexpected result: {:?}", other),
+    }
+}
+
+#[test]
+fn test_unreadable_directory_is_an_error() {
+    let root = tempfile::tempdir().unwrap();
+    write(root.path(), "usr/lib/sysctl.d/50-default.conf", "vm.swappiness = 60\n");
+    // ディレクトリの代わりにファイルがあると読めない（NotFound 以外のエラー）
+    write(root.path(), "etc/sysctl.d", "not a directory\n");
+
+    let result = SysctlLoader::with_root(root.path()).load();
+
+    assert!(matches!(result, Err(ParseError::InFile { path, .. }) if path.ends_with("etc/sysctl.d")));
+}
+
+#[cfg(unix)]
+#[test]
+fn test_symlinked_sysctl_conf_is_read_once() {
+    let root = tempfile::tempdir().unwrap();
+    write(root.path(), "etc/sysctl.conf", "vm.swappiness = 5\n");
+    write(root.path(), "usr/lib/sysctl.d/50-default.conf", "vm.swappiness = 60\n");
+    fs::create_dir_all(root.path().join("etc/sysctl.d")).unwrap();
+    std::os::unix::fs::symlink("../sysctl.conf", root.path().join("etc/sysctl.d/99-sysctl.conf")).unwrap();
+
+    let loaded = SysctlLoader::with_root(root.path()).load().unwrap();
+
+    assert_eq!(
+        loaded.files,
+        [root.path().join("usr/lib/sysctl.d/50-default.conf"), root.path().join("etc/sysctl.d/99-sysctl.conf")]
+    );
+    assert_eq!(loaded.to_map().get("vm.swappiness"), Some(&"5".to_string()));
+}
+
+#[cfg(unix)]
+#[test]
+fn test_absolute_symlink_is_resolved_inside_root() {
+    let root = tempfile::tempdir().unwrap();
+    write(root.path(), "etc/sysctl.conf", "vm.swappiness = 5\n");
+    write(root.path(), "usr/lib/sysctl.d/60-tuning.conf", "kernel.pid_max = 1000\n");
+    fs::create_dir_all(root.path().join("etc/sysctl.d")).unwrap();
+    std::os::unix::fs::symlink("/etc/sysctl.conf", root.path().join("etc/sysctl.d/99-sysctl.conf")).unwrap();
+    std::os::unix::fs::symlink("/usr/lib/sysctl.d/60-tuning.conf", root.path().join("etc/sysctl.d/70-alias.conf")).unwrap();
+
+    let loaded = SysctlLoader::with_root(root.path()).load().unwrap();
+
+    // ホストの /etc/sysctl.conf ではなく root の中のファイルを、それぞれ1回だけ読む
+    assert_eq!(
+        loaded.files,
+        [root.path().join("usr/lib/sysctl.d/60-tuning.conf"), root.path().join("etc/sysctl.d/99-sysctl.conf")]
+    );
+    assert_eq!(loaded.to_map().get("vm.swappiness"), Some(&"5".to_string()));
+    assert_eq!(loaded.to_map().get("kernel.pid_max"), Some(&"1000".to_string()));
+    assert_eq!(loaded.source_of("vm.swappiness"), Some(root.path().join("etc/sysctl.d/99-sysctl.conf").as_path()));
+}
+
+#[test]
+fn test_loader_uses_parser_options() {
+    let root = tempfile::tempdir().unwrap();
+    write(root.path(), "etc/sysctl.d/50-rmem.conf", "net.ipv4.tcp_rmem = 4096 \\\n    87380\n");
+
+    let loaded = SysctlLoader::with_root(root.path())
+        .options(ParserOptions::new().line_continuation(true))
+        .load()
+        .unwrap();
+
+    assert_eq!(loaded.to_map().get("net.ipv4.tcp_rmem"), Some(&"4096 87380".to_string()));
+    assert!(SysctlLoader::with_root(root.path()).load().is_err());
+}
@@ -0,0 +1,210 @@
+use confparser::{
+    ConfDocument,
+    DuplicatePolicy,
+    ParseError,
+    ParserOptions,
+    TrimPolicy,
+    parse_str,
+    parse_str_with,
+};
+
+#[test]
+fn test_default_options_match_parse_str() {
+    let input = std::fs::read_to_string("conf/sysctl.conf").unwrap();
+
+    assert_eq!(parse_str_with(&input, &ParserOptions::default()).unwrap(), parse_str(&input).unwrap());
+}
+
+#[test]
+fn test_custom_comment_prefixes() {
+    let input = "
+        // slash comment
+        ; no longer a comment = 1
+        key = value
+    ";
+    let options = ParserOptions::new().comment_prefixes(["#", "//"]);
+
+    let result = parse_str_with(input, &options).unwrap();
+
+    assert_eq!(result.get("key"), Some(&"value".to_string()));
+    assert_eq!(result.get("; no longer a comment"), Some(&"1".to_string()));
+}
+
+#[test]
+fn test_inline_comments_disabled() {
+    let input = "url = http://example.com/#top\n";
+    let options = ParserOptions::new().inline_comments(false);
+
+    let result = parse_str_with(input, &options).unwrap();
+
+    assert_eq!(result.get("url"), Some(&"http://example.com/#top".to_string()));
+    assert_eq!(ConfDocument::parse_with(input, &options).to_string(), input);
+}
+
+#[test]
+fn test_max_value_length() {
+    let options = ParserOptions::new().max_value_length(Some(4));
+
+    let result = parse_str_with("short = abcd\nlong = abcde\n", &options);
+    assert!(matches!(result, Err(ParseError::ValueTooLong { line_number: 2, length: 5, max: 4, .. })));
+
+    let unlimited = ParserOptions::new().max_value_length(None);
+    assert!(parse_str_with(&format!("long = {}", "a".repeat(5000)), &unlimited).is_ok());
+}
+
+#[test]
+fn test_max_lines() {
+    let options = ParserOptions::new().max_lines(Some(2));
+
+    assert!(parse_str_with("a = 1\nb = 2\n", &options).is_ok());
+    assert!(matches!(
+        parse_str_with("a = 1\nb = 2\nc = 3\n", &options),
+        Err(ParseError::TooManyLines { line_number: 3, limit: 2 })
+    ));
+}
+
+#[test]
+fn test_empty_values_rejected() {
+    let options = ParserOptions::new().allow_empty_values(false);
+
+    let result = parse_str_with("fs.file-max =\n", &options);
+    assert!(matches!(result, Err(ParseError::EmptyValue { line_number: 1, ref key }) if key == "fs.file-max"));
+}
+
+#[test]
+fn test_trim_policy() {
+    let input = "  key  =  value  \n";
+
+    let keys_only = parse_str_with(input, &ParserOptions::new().trim(TrimPolicy::KeysOnly)).unwrap();
+    assert_eq!(keys_only.get("key"), Some(&"  value  ".to_string()));
+
+    let none = parse_str_with(input, &ParserOptions::new().trim(TrimPolicy::None)).unwrap();
+    assert_eq!(none.get("  key  "), Some(&"  value  ".to_string()));
+}
+
+#[test]
+fn test_normalize_keys_and_duplicate_policy() {
+    let input = "a/b = 1\na.b = 2\n";
+
+    let raw = parse_str_with(input, &ParserOptions::new().normalize_keys(false)).unwrap();
+    assert_eq!(raw.len(), 2);
+
+    let first = parse_str_with(input, &ParserOptions::new().duplicate_policy(DuplicatePolicy::FirstWins)).unwrap();
+    assert_eq!(first.get("a.b"), Some(&"1".to_string()));
+}
+
+#[test]
+fn test_quoted_values_keep_hash() {
+    let input = r#"
+        user.banner = "build #42"   # trailing comment
+        user.single = 'it''s'
+        user.escaped = build \#43
+        user.plain = value # comment
+    "#;
+    let options = ParserOptions::new().quoted_values(true);
+
+    let result = parse_str_with(input, &options);
+    assert!(result.is_err(), "text after the closing quote must be rejected");
+
+    let input = input.replace("'it''s'", r"'it\'s'");
+    let result = parse_str_with(&input, &options).unwrap();
+
+    assert_eq!(result.get("user.banner"), Some(&"build #42".to_string()));
+    assert_eq!(result.get("user.single"), Some(&"it's".to_string()));
+    assert_eq!(result.get("user.escaped"), Some(&"build #43".to_string()));
+    assert_eq!(result.get("user.plain"), Some(&"value".to_string()));
+}
+
+#[test]
+fn test_quoted_value_escapes() {
+    let input = r#"motd = "say \"hi\"\n\\ done""#;
+
+    let result = parse_str_with(input, &ParserOptions::new().quoted_values(true)).unwrap();
+
+    assert_eq!(result.get("motd"), Some(&"say \"hi\"\n\\ done".to_string()));
+}
+
+#[test]
+fn test_unterminated_quote_is_invalid() {
+    let result = parse_str_with("user.banner = \"build #42\n", &ParserOptions::new().quoted_values(true));
+
+    assert!(matches!(result, Err(ParseError::InvalidLine { line_number: 1, .. })));
+}
+
+#[test]
+fn test_quotes_are_plain_text_by_default() {
+    let result = parse_str("user.banner = \"build #42\"").unwrap();
+
+    assert_eq!(result.get("user.banner"), Some(&"\"build".to_string()));
+}
+
+#[test]
+fn test_quoted_document_roundtrip_and_set() {
+    let input = "user.banner = 'build #42'  # note\nuser.motd = hello\n";
+    let options = ParserOptions::new().quoted_values(true);
+    let mut doc = ConfDocument::parse_with(input, &options);
+
+    assert_eq!(doc.to_string(), input);
+    let (_, entry) = doc.entries().next().unwrap();
+    assert_eq!(entry.quote, Some('\''));
+    assert_eq!(entry.value, "build #42");
+
+    doc.set("user.banner", "build #43").unwrap();
+    doc.set("user.motd", "see #help").unwrap();
+
+    assert_eq!(doc.to_string(), "user.banner = 'build #43'  # note\nuser.motd = \"see #help\"\n");
+    assert_eq!(doc.get("user.motd"), Some("see #help"));
+}
+
+#[test]
+fn test_line_continuation_joins_lines() {
+    let input = "kernel.core_pattern = |/usr/bin/handler \\\n    --pid %p \\\n    --sig %s\nvm.swappiness = 10\n";
+    let options = ParserOptions::new().line_continuation(true);
+
+    let result = parse_str_with(input, &options).unwrap();
+
+    assert_eq!(result.get("kernel.core_pattern"), Some(&"|/usr/bin/handler --pid %p --sig %s".to_string()));
+    assert_eq!(result.get("vm.swappiness"), Some(&"10".to_string()));
+}
+
+#[test]
+fn test_line_continuation_is_off_by_default() {
+    let result = parse_str("kernel.core_pattern = a \\\nb = c\n").unwrap();
+
+    assert_eq!(result.get("kernel.core_pattern"), Some(&"a \\".to_string()));
+    assert_eq!(result.get("b"), Some(&"c".to_string()));
+}
+
+#[test]
+fn test_line_continuation_checks_joined_length() {
+    let input = format!("first = 1\nuser.long = {} \\\n{}\n", "a".repeat(3000), "b".repeat(3000));
+    let options = ParserOptions::new().line_continuation(true);
+
+    let result = parse_str_with(&input, &options);
+
+    assert!(matches!(result, Err(ParseError::ValueTooLong { line_number: 2, length: 6001, .. })));
+}
+
+#[test]
+fn test_line_continuation_reports_physical_line_numbers() {
+    let input = "a = 1 \\\n  2 \\\n  3\nthis is invalid\n";
+    let options = ParserOptions::new().line_continuation(true);
+
+    let result = parse_str_with(input, &options);
+
+    assert!(matches!(result, Err(ParseError::InvalidLine { line_number: 4, .. })));
+}
+
+#[test]
+fn test_line_continuation_document_roundtrip() {
+    let input = "# head\na = 1 \\\n  2\r\nb = 3\n";
+    let options = ParserOptions::new().line_continuation(true);
+    let mut doc = ConfDocument::parse_with(input, &options);
+
+    assert_eq!(doc.to_string(), input);
+    assert_eq!(doc.entries().map(|(n, e)| (n, e.key.as_str())).collect::<Vec<_>>(), vec![(2, "a"), (4, "b")]);
+
+    doc.set("a", "12").unwrap();
+
+    assert_eq!(doc.to_string(), "# head\na = 12\r\nb = 3\n");
+}
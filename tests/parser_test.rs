@@ -2,8 +2,17 @@
 use confparser::{
     ParseError,
     parse_str,
+    parse_str_with_spans,
+    parse_str_with_spans_with,
+    parse_file_with_spans,
+    ParserOptions,
+    parse_str_recovering,
+    parse_str_with_policy,
+    parse_file_with_policy,
+    DuplicatePolicy,
     flatten_to_nested_json,
 };
+use confparser::parser::ShadowedAssignment;
 use serde_json::json;
 
 #[test]
@@ -77,7 +86,7 @@ fn test_semicolon_comment_line_only() {
 }
 
 #[test]
-fn test_dash_prefix_line_is_ignored() {
+fn test_dash_prefix_line_is_parsed() {
     let input = "
         -net.ipv4.conf.all.rp_filter = 1
         kernel.pid_max = 65535
@@ -85,10 +94,28 @@ fn test_dash_prefix_line_is_ignored() {
 
     let result = parse_str(input).unwrap();
 
-    assert!(result.get("net.ipv4.conf.all.rp_filter").is_none());
+    assert_eq!(result.get("net.ipv4.conf.all.rp_filter").unwrap(), "1");
     assert_eq!(result.get("kernel.pid_max").unwrap(), "65535");
 }
 
+#[test]
+fn test_dash_prefix_sets_ignore_failure_flag() {
+    let input = "-net.ipv4.conf.all.rp_filter = 1\nkernel.pid_max = 65535\n";
+
+    let result = parse_str_with_spans(input).unwrap();
+
+    let rp_filter = result.get("net.ipv4.conf.all.rp_filter").unwrap();
+    assert!(rp_filter.ignore_failure);
+    assert_eq!(&rp_filter.span.raw_line[rp_filter.span.key_range.clone()], "net.ipv4.conf.all.rp_filter");
+    assert!(!result.get("kernel.pid_max").unwrap().ignore_failure);
+}
+
+#[test]
+fn test_dash_without_key_is_invalid() {
+    let result = parse_str("- = 1");
+    assert!(matches!(result, Err(ParseError::InvalidLine { line_number: 1, .. })));
+}
+
 #[test]
 fn test_value_too_long_error() {
     let long_value = "a".repeat(4097);
@@ -96,4 +123,147 @@ fn test_value_too_long_error() {
 
     let result = parse_str(&input);
     assert!(matches!(result, Err(ParseError::ValueTooLong { .. })));
-}
\ No newline at end of file
+}
+#[test]
+fn test_parse_str_with_spans() {
+    let input = "# comment\n  net.core.somaxconn = 1024   # inline\nvm.swappiness=60\n";
+
+    let result = parse_str_with_spans(input).unwrap();
+
+    let somaxconn = result.get("net.core.somaxconn").unwrap();
+    assert_eq!(somaxconn.value, "1024");
+    assert_eq!(somaxconn.span.line_number, 2);
+    assert_eq!(somaxconn.span.raw_line, "  net.core.somaxconn = 1024   # inline");
+    assert_eq!(&somaxconn.span.raw_line[somaxconn.span.key_range.clone()], "net.core.somaxconn");
+    assert_eq!(&somaxconn.span.raw_line[somaxconn.span.value_range.clone()], "1024");
+    assert_eq!(somaxconn.span.key_column(), 3);
+    assert_eq!(somaxconn.span.value_column(), 24);
+
+    let swappiness = result.get("vm.swappiness").unwrap();
+    assert_eq!(swappiness.span.line_number, 3);
+    assert_eq!(swappiness.span.path, None);
+}
+
+#[test]
+fn test_parse_str_with_spans_with_options() {
+    let input = "// comment\nnet.ipv4.tcp_rmem = 4096 \\\n    87380\nvm.swappiness = 60\nvm.swappiness = 10\n";
+    let options = ParserOptions::new()
+        .comment_prefixes(["//"])
+        .line_continuation(true)
+        .duplicate_policy(DuplicatePolicy::FirstWins);
+
+    let result = parse_str_with_spans_with(input, &options).unwrap();
+
+    // 継続行の raw_line はつないだ論理行で、範囲もそれに対するもの
+    let rmem = result.get("net.ipv4.tcp_rmem").unwrap();
+    assert_eq!(rmem.span.line_number, 2);
+    assert_eq!(rmem.span.raw_line, "net.ipv4.tcp_rmem = 4096 87380");
+    assert_eq!(&rmem.span.raw_line[rmem.span.value_range.clone()], "4096 87380");
+
+    let swappiness = result.get("vm.swappiness").unwrap();
+    assert_eq!((swappiness.value.as_str(), swappiness.span.line_number), ("60", 4));
+
+    let error = ParserOptions::new().duplicate_policy(DuplicatePolicy::Error);
+    assert!(matches!(
+        parse_str_with_spans_with("a = 1\na = 2\n", &error),
+        Err(ParseError::DuplicateKey { first_line: 1, line_number: 2, .. })
+    ));
+}
+
+#[test]
+fn test_parse_file_with_spans_records_path() {
+    let result = parse_file_with_spans("conf/sysctl.conf").unwrap();
+
+    let entry = result.get("kernel.shmall").unwrap();
+    assert_eq!(entry.span.path.as_deref(), Some(std::path::Path::new("conf/sysctl.conf")));
+    assert_eq!(entry.span.line_number, 6);
+    assert_eq!(entry.span.to_string(), "conf/sysctl.conf:6:1");
+}
+
+#[test]
+fn test_parse_str_recovering_collects_all_errors() {
+    let input = format!(
+        "kernel.shmall = 2097152\ninvalid line\nvm.swappiness = 60\nkernel.long_value = {}\nanother bad line\n",
+        "a".repeat(4097)
+    );
+
+    let (map, errors) = parse_str_recovering(&input);
+
+    assert_eq!(map.len(), 2);
+    assert_eq!(map.get("vm.swappiness"), Some(&"60".to_string()));
+    assert_eq!(errors.len(), 3);
+    assert!(matches!(errors[0], ParseError::InvalidLine { line_number: 2, .. }));
+    assert!(matches!(errors[1], ParseError::ValueTooLong { line_number: 4, .. }));
+    assert!(matches!(errors[2], ParseError::InvalidLine { line_number: 5, .. }));
+}
+
+#[test]
+fn test_parse_str_recovering_without_errors_matches_parse_str() {
+    let input = "endpoint = localhost:3000\n# comment\nlog.file = /var/log/console.log\n";
+
+    let (map, errors) = parse_str_recovering(input);
+
+    assert!(errors.is_empty());
+    assert_eq!(map, parse_str(input).unwrap());
+}
+
+const DUPLICATE_INPUT: &str = "
+net.ipv4.ip_forward = 1
+vm.swappiness = 60
+net.ipv4.ip_forward = false
+net/ipv4/ip_forward = 0
+";
+
+#[test]
+fn test_last_wins_reports_shadowed() {
+    let report = parse_str_with_policy(DUPLICATE_INPUT, DuplicatePolicy::LastWins).unwrap();
+
+    assert_eq!(report.values.get("net.ipv4.ip_forward"), Some(&"0".to_string()));
+    assert!(report.all_values.is_empty());
+    assert_eq!(report.shadowed, vec![
+        ShadowedAssignment { key: "net.ipv4.ip_forward".to_string(), value: "1".to_string(), line_number: 2, winning_line: 5 },
+        ShadowedAssignment { key: "net.ipv4.ip_forward".to_string(), value: "false".to_string(), line_number: 4, winning_line: 5 },
+    ]);
+}
+
+#[test]
+fn test_first_wins() {
+    let report = parse_str_with_policy(DUPLICATE_INPUT, DuplicatePolicy::FirstWins).unwrap();
+
+    assert_eq!(report.values.get("net.ipv4.ip_forward"), Some(&"1".to_string()));
+    assert_eq!(report.shadowed.len(), 2);
+    assert!(report.shadowed.iter().all(|s| s.winning_line == 2));
+    assert_eq!(report.shadowed[0].line_number, 4);
+}
+
+#[test]
+fn test_error_policy() {
+    let result = parse_str_with_policy(DUPLICATE_INPUT, DuplicatePolicy::Error);
+
+    assert!(matches!(
+        result,
+        Err(ParseError::DuplicateKey { ref key, first_line: 2, line_number: 4 }) if key == "net.ipv4.ip_forward"
+    ));
+}
+
+#[test]
+fn test_collect_all() {
+    let report = parse_str_with_policy(DUPLICATE_INPUT, DuplicatePolicy::CollectAll).unwrap();
+
+    assert_eq!(
+        report.all_values.get("net.ipv4.ip_forward"),
+        Some(&vec!["1".to_string(), "false".to_string(), "0".to_string()])
+    );
+    assert_eq!(report.all_values.get("vm.swappiness"), Some(&vec!["60".to_string()]));
+    assert_eq!(report.shadowed.len(), 2);
+}
+
+#[test]
+fn test_sample_file_duplicates() {
+    let report = parse_file_with_policy("conf/sysctl.conf", DuplicatePolicy::LastWins).unwrap();
+
+    assert_eq!(report.shadowed.len(), 1);
+    assert_eq!(report.shadowed[0].key, "net.ipv4.ip_forward");
+    assert_eq!(report.shadowed[0].line_number, 9);
+    assert_eq!(report.shadowed[0].winning_line, 24);
+}
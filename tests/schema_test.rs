@@ -1,5 +1,5 @@
 use std::collections::BTreeMap;
-use confparser::{parse_schema_str, validate_with_schema, parse_str, schema::{SchemaType, SchemaEntry}, ParseError};
+use confparser::{parse_schema_str, parse_schema_str_with, ParserOptions, migrate_schema_str, parse_str, validate_with_schema, validate_spanned_with_schema, parse_str_with_spans, schema::{SchemaType, SchemaEntry}, ParseError};
 
 #[test]
 fn test_parse_valid_schema() {
@@ -242,4 +242,151 @@ fn test_default_value_is_inserted() {
     // ✅ default が補完されていることを確認
     assert_eq!(config.get("log.level"), Some(&"info".to_string()));
     assert_eq!(config.get("timeout"), Some(&"30".to_string()));
-}
\ No newline at end of file
+}
+
+#[test]
+fn test_validate_spanned_reports_location() {
+    let config = parse_str_with_spans("debug = true\nlog.max =   abc\n").unwrap();
+
+    let schema = BTreeMap::from([
+        ("log.max".to_string(), SchemaEntry { typ: SchemaType::Int, required: false, default: None }),
+        ("timeout".to_string(), SchemaEntry { typ: SchemaType::Int, required: false, default: Some("30".to_string()) }),
+    ]);
+
+    let errors = validate_spanned_with_schema(&config, &schema).unwrap_err();
+    assert_eq!(errors, vec!["<input>:2:13: log.max: 'abc' is not a valid Int (`log.max =   abc`)".to_string()]);
+}
+
+#[test]
+fn test_validate_spanned_returns_completed_values() {
+    let config = parse_str_with_spans("log.max = 100\n").unwrap();
+
+    let schema = BTreeMap::from([
+        ("log.max".to_string(), SchemaEntry { typ: SchemaType::Int, required: false, default: None }),
+        ("timeout".to_string(), SchemaEntry { typ: SchemaType::Int, required: false, default: Some("30".to_string()) }),
+    ]);
+
+    let values = validate_spanned_with_schema(&config, &schema).unwrap();
+    assert_eq!(values.get("log.max"), Some(&"100".to_string()));
+    assert_eq!(values.get("timeout"), Some(&"30".to_string()));
+}
+
+#[test]
+fn test_schema_lookup_uses_normalized_keys() {
+    let schema = parse_schema_str("net/ipv4/ip_forward = bool").unwrap();
+    let mut config = parse_str("net.ipv4.ip_forward = maybe").unwrap();
+
+    let errors = validate_with_schema(&mut config, &schema).unwrap_err();
+    assert_eq!(errors.len(), 1);
+    assert!(errors[0].contains("net.ipv4.ip_forward"));
+}
+
+#[test]
+fn test_list_schema_type() {
+    let schema = parse_schema_str("net.ipv4.tcp_rmem = int[]\n").unwrap();
+    assert_eq!(schema["net.ipv4.tcp_rmem"].typ, SchemaType::List(Box::new(SchemaType::Int)));
+
+    let mut valid = BTreeMap::from([("net.ipv4.tcp_rmem".to_string(), "4096 87380 6291456".to_string())]);
+    assert!(validate_with_schema(&mut valid, &schema).is_ok());
+
+    let mut invalid = BTreeMap::from([("net.ipv4.tcp_rmem".to_string(), "4096 big".to_string())]);
+    assert!(validate_with_schema(&mut invalid, &schema).is_err());
+}
+
+#[test]
+fn test_string_options_with_max_length() {
+    let schema = parse_schema_str("user.name = string(max=20, required, default=guest)\n").unwrap();
+
+    assert_eq!(
+        schema["user.name"],
+        SchemaEntry { typ: SchemaType::String(Some(20)), required: true, default: Some("guest".to_string()) }
+    );
+
+    let mut too_long = BTreeMap::from([("user.name".to_string(), "x".repeat(21))]);
+    assert!(validate_with_schema(&mut too_long, &schema).is_err());
+}
+
+#[test]
+fn test_legacy_string_length_is_kept() {
+    let schema = parse_schema_str("user.setting = string(20)\nage = int(default=20)\n").unwrap();
+
+    assert_eq!(schema["user.setting"].typ, SchemaType::String(Some(20)));
+    assert_eq!(schema["age"].default.as_deref(), Some("20"));
+    assert_eq!(SchemaType::from_str("string(20)"), Some(SchemaType::String(Some(20))));
+    assert_eq!(SchemaType::from_str("string(required)"), None);
+}
+
+#[test]
+fn test_quoted_default_and_enum_options() {
+    let schema = parse_schema_str("motd = string(default=\"hello, world\")\nlog.type = [\"file\", \"console\"](default=file)\n").unwrap();
+
+    assert_eq!(schema["motd"].default.as_deref(), Some("hello, world"));
+    assert_eq!(schema["log.type"].typ, SchemaType::Enum(vec!["file".to_string(), "console".to_string()]));
+    assert_eq!(schema["log.type"].default.as_deref(), Some("file"));
+}
+
+#[test]
+fn test_schema_mis_parses_are_errors() {
+    let cases = [
+        "a = string(max=abc)",
+        "a = string(20",
+        "a = string(20) extra",
+        "a = int(max=5)",
+        "a = int(requried)",
+        "a = int(required, required)",
+        "a = int(default=abc)",
+        "a = string(, required)",
+        "a = [\"x\", ]",
+        "a = [\"x\"",
+        "a = (required)",
+    ];
+
+    for input in cases {
+        let result = parse_schema_str(&format!("ok = int\n{}\n", input));
+        assert!(matches!(result, Err(ParseError::InvalidLine { line_number: 2, .. })), "{} should be rejected", input);
+    }
+}
+
+#[test]
+fn test_migrate_schema_str() {
+    let input = "# 旧来の書式\nuser.setting = string(20)\nlog.level = string(default=info)\nmode = [\"auto\",\"manual\"]\n";
+
+    let migrated = migrate_schema_str(input).unwrap();
+
+    assert_eq!(
+        migrated,
+        "# 旧来の書式\nuser.setting = string(max=20)\nlog.level = string(default=info)\nmode = [\"auto\", \"manual\"]\n"
+    );
+    assert_eq!(parse_schema_str(&migrated).unwrap(), parse_schema_str(input).unwrap());
+    assert_eq!(migrate_schema_str(&migrated).unwrap(), migrated);
+}
+
+#[test]
+fn test_repository_schema_file_parses() {
+    let schema = parse_schema_str(&std::fs::read_to_string("conf/schema.conf").unwrap()).unwrap();
+
+    assert_eq!(schema["user.setting"].typ, SchemaType::String(Some(20)));
+    assert!(schema["pnrnc485"].required);
+}
+
+#[test]
+fn test_float_rejects_non_finite_values() {
+    let schema = parse_schema_str("vm.ratio = float\n").unwrap();
+
+    for value in ["NaN", "inf", "-infinity"] {
+        let mut config = BTreeMap::from([("vm.ratio".to_string(), value.to_string())]);
+        assert!(validate_with_schema(&mut config, &schema).is_err(), "{} should be rejected", value);
+        assert_eq!(SchemaType::Float.to_json(value), None);
+    }
+    assert_eq!(SchemaType::Float.to_json("1.5"), Some(serde_json::json!(1.5)));
+}
+
+#[test]
+fn test_parse_schema_str_with_options() {
+    let options = ParserOptions::new().comment_prefixes(["//"]);
+
+    let schema = parse_schema_str_with("// 型の定義\nvm.swappiness = int(default=60)\n", &options).unwrap();
+
+    assert_eq!(schema["vm.swappiness"].typ, SchemaType::Int);
+    assert!(parse_schema_str("// 型の定義\nvm.swappiness = int\n").is_err());
+}
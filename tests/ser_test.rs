@@ -0,0 +1,119 @@
+use std::collections::{BTreeMap, HashMap};
+use confparser::{ParseError, from_str, to_string};
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+struct Provisioning {
+    net: Net,
+    vm: Vm,
+    comment: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+struct Net {
+    ip_forward: bool,
+    tcp_rmem: Vec<u32>,
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+struct Vm {
+    swappiness: u8,
+    overcommit_ratio: f64,
+}
+
+fn sample() -> Provisioning {
+    Provisioning {
+        net: Net {
+            ip_forward: true,
+            tcp_rmem: vec![4096, 87380, 6291456],
+        },
+        vm: Vm {
+            swappiness: 10,
+            overcommit_ratio: 1.5,
+        },
+        comment: None,
+    }
+}
+
+#[test]
+fn test_to_string_flattens_struct_in_field_order() {
+    let output = to_string(&sample()).unwrap();
+
+    assert_eq!(
+        output,
+        "net.ip_forward = 1\nnet.tcp_rmem = 4096 87380 6291456\nvm.swappiness = 10\nvm.overcommit_ratio = 1.5\n"
+    );
+}
+
+#[test]
+fn test_to_string_roundtrips_through_from_str() {
+    let output = to_string(&sample()).unwrap();
+
+    assert_eq!(from_str::<Provisioning>(&output).unwrap(), sample());
+}
+
+#[test]
+fn test_maps_are_written_in_key_order() {
+    let mut conf = HashMap::new();
+    conf.insert("eth1".to_string(), 2);
+    conf.insert("eth0/100".to_string(), 1);
+    conf.insert("all".to_string(), 0);
+    let mut root = BTreeMap::new();
+    root.insert("rp_filter", conf);
+
+    let output = to_string(&root).unwrap();
+
+    assert_eq!(output, "rp_filter.all = 0\nrp_filter.eth0/100 = 1\nrp_filter.eth1 = 2\n");
+}
+
+#[test]
+fn test_flat_map_with_dotted_keys() {
+    let flat = BTreeMap::from([("net.ipv4.ip_forward", 1), ("vm.swappiness", 10), ("net/ipv4/conf/eth0.100/rp_filter", 2)]);
+
+    let output = to_string(&flat).unwrap();
+
+    assert_eq!(output, "net.ipv4.ip_forward = 1\nnet.ipv4.conf.eth0/100.rp_filter = 2\nvm.swappiness = 10\n");
+    let reparsed = confparser::parse_str(&output).unwrap();
+    assert_eq!(reparsed.get("net.ipv4.ip_forward").map(String::as_str), Some("1"));
+    assert_eq!(reparsed.get("net.ipv4.conf.eth0/100.rp_filter").map(String::as_str), Some("2"));
+}
+
+#[test]
+fn test_unrepresentable_values_are_errors() {
+    let nested = BTreeMap::from([("matrix", vec![vec![1, 2], vec![3]])]);
+    assert!(matches!(to_string(&nested), Err(ParseError::Serde(msg)) if msg.contains("nested sequence")));
+
+    let int_keys = BTreeMap::from([("ports", BTreeMap::from([(80, "http")]))]);
+    assert!(matches!(to_string(&int_keys), Err(ParseError::Serde(msg)) if msg.contains("non-string keys")));
+
+    assert!(matches!(to_string(&42), Err(ParseError::Serde(_))));
+}
+
+#[test]
+fn test_values_that_break_the_line_are_rejected() {
+    let newline = BTreeMap::from([("motd", "hello\nworld")]);
+    assert!(matches!(to_string(&newline), Err(ParseError::InvalidValue { key, .. }) if key == "motd"));
+
+    let comment = BTreeMap::from([("net", BTreeMap::from([("banner", "build #42")]))]);
+    assert!(matches!(to_string(&comment), Err(ParseError::InvalidValue { key, .. }) if key == "net.banner"));
+
+    let long = BTreeMap::from([("big", "x".repeat(4097))]);
+    assert!(matches!(to_string(&long), Err(ParseError::InvalidValue { key, .. }) if key == "big"));
+}
+
+#[derive(Serialize)]
+struct Colliding {
+    a: BTreeMap<String, i32>,
+    #[serde(flatten)]
+    extra: BTreeMap<String, BTreeMap<String, i32>>,
+}
+
+#[test]
+fn test_colliding_keys_are_errors() {
+    let value = Colliding {
+        a: BTreeMap::from([("x".to_string(), 1)]),
+        extra: BTreeMap::from([("a".to_string(), BTreeMap::from([("x".to_string(), 2)]))]),
+    };
+
+    assert!(matches!(to_string(&value), Err(ParseError::Serde(msg)) if msg.contains("'a.x'")));
+}
@@ -0,0 +1,69 @@
+use std::io::{BufReader, Cursor};
+use confparser::{EntryReader, ParseError, ParserOptions, StreamEntry, entry_stream};
+use futures_util::StreamExt;
+
+const INPUT: &str = "# comment\nnet/ipv4/ip_forward = 1\n\n-kernel.yama.ptrace_scope = 2\r\nvm.swappiness = 10";
+
+#[test]
+fn test_entry_reader_yields_entries_with_line_numbers() {
+    let entries: Vec<StreamEntry> = EntryReader::new(Cursor::new(INPUT)).collect::<Result<_, _>>().unwrap();
+
+    let summary: Vec<_> = entries
+        .iter()
+        .map(|e| (e.line_number, e.key.as_str(), e.value.as_str(), e.ignore_failure))
+        .collect();
+    assert_eq!(
+        summary,
+        vec![
+            (2, "net.ipv4.ip_forward", "1", false),
+            (4, "kernel.yama.ptrace_scope", "2", true),
+            (5, "vm.swappiness", "10", false),
+        ]
+    );
+}
+
+#[test]
+fn test_entry_reader_continues_after_invalid_line() {
+    let input = "a = 1\nthis is invalid\nb = 2\n";
+
+    let results: Vec<_> = EntryReader::new(BufReader::new(input.as_bytes())).collect();
+
+    assert_eq!(results.len(), 3);
+    assert!(matches!(results[1], Err(ParseError::InvalidLine { line_number: 2, .. })));
+    assert_eq!(results[2].as_ref().unwrap().key, "b");
+}
+
+#[test]
+fn test_entry_reader_respects_options() {
+    let input = "a = 1 \\\n  2\nb = 3\nc = 4\n";
+    let options = ParserOptions::new().line_continuation(true).max_lines(Some(3));
+
+    let results: Vec<_> = EntryReader::with_options(Cursor::new(input), &options).collect();
+
+    assert_eq!(results.len(), 3);
+    assert_eq!(results[0].as_ref().unwrap().value, "1 2");
+    assert_eq!(results[1].as_ref().unwrap().line_number, 3);
+    assert!(matches!(results[2], Err(ParseError::TooManyLines { line_number: 4, limit: 3 })));
+}
+
+#[test]
+fn test_entry_reader_reports_invalid_utf8() {
+    let input: &[u8] = b"a = 1\nb = \xff\n";
+
+    let results: Vec<_> = EntryReader::new(input).collect();
+
+    assert_eq!(results.len(), 2);
+    assert!(matches!(results[1], Err(ParseError::Io(_))));
+}
+
+#[tokio::test]
+async fn test_entry_stream_matches_entry_reader() {
+    let expected: Vec<_> = EntryReader::new(Cursor::new(INPUT)).map(Result::unwrap).collect();
+
+    let entries: Vec<_> = entry_stream(INPUT.as_bytes(), &ParserOptions::default())
+        .map(Result::unwrap)
+        .collect()
+        .await;
+
+    assert_eq!(entries, expected);
+}
@@ -0,0 +1,85 @@
+use std::net::{IpAddr, Ipv4Addr};
+use std::time::Duration;
+use confparser::{ByteSize, FromConfValue, ParseError, parse_config};
+
+const INPUT: &str = "\
+vm.swappiness = 60
+vm.overcommit_ratio = 1.5
+net.ipv4.ip_forward = 1
+kernel.debug = yes
+feature.enabled = False
+net.ipv4.tcp_rmem = 4096 87380 6291456
+service.timeout = 1h30m
+service.retry = 500ms
+service.interval = 15
+cache.size = 16MiB
+listen.addr = 192.168.0.1
+listen.v6 = ::1
+";
+
+#[test]
+fn test_get_numbers() {
+    let config = parse_config(INPUT).unwrap();
+
+    assert_eq!(config.get::<i64>("vm.swappiness").unwrap(), 60);
+    assert_eq!(config.get::<u8>("vm.swappiness").unwrap(), 60);
+    assert_eq!(config.get::<f64>("vm.overcommit_ratio").unwrap(), 1.5);
+    assert_eq!(config.get::<Vec<u32>>("net.ipv4.tcp_rmem").unwrap(), vec![4096, 87380, 6291456]);
+}
+
+#[test]
+fn test_get_bool() {
+    let config = parse_config(INPUT).unwrap();
+
+    assert!(config.get::<bool>("net.ipv4.ip_forward").unwrap());
+    assert!(config.get::<bool>("kernel.debug").unwrap());
+    assert!(!config.get::<bool>("feature.enabled").unwrap());
+    assert!(config.get::<bool>("vm.swappiness").is_err());
+}
+
+#[test]
+fn test_get_durations_and_sizes() {
+    let config = parse_config(INPUT).unwrap();
+
+    assert_eq!(config.get::<Duration>("service.timeout").unwrap(), Duration::from_secs(90 * 60));
+    assert_eq!(config.get::<Duration>("service.retry").unwrap(), Duration::from_millis(500));
+    assert_eq!(config.get::<Duration>("service.interval").unwrap(), Duration::from_secs(15));
+    assert_eq!(config.get::<ByteSize>("cache.size").unwrap(), ByteSize(16 * 1024 * 1024));
+    assert_eq!(config.get::<ByteSize>("net.ipv4.ip_forward").unwrap(), ByteSize(1));
+}
+
+#[test]
+fn test_get_ip_addresses() {
+    let config = parse_config(INPUT).unwrap();
+
+    assert_eq!(config.get::<Ipv4Addr>("listen.addr").unwrap(), Ipv4Addr::new(192, 168, 0, 1));
+    assert!(config.get::<IpAddr>("listen.v6").unwrap().is_loopback());
+    assert!(config.get::<Ipv4Addr>("listen.v6").is_err());
+}
+
+#[test]
+fn test_get_or_and_errors() {
+    let config = parse_config(INPUT).unwrap();
+
+    assert_eq!(config.get_or("vm.missing", 10u32).unwrap(), 10);
+    assert_eq!(config.get_or("vm.swappiness", 10u32).unwrap(), 60);
+    assert_eq!(config.get_opt::<u32>("vm.missing").unwrap(), None);
+
+    let missing = config.get::<u32>("vm.missing").unwrap_err();
+    assert!(matches!(&missing, ParseError::MissingKey { key } if key == "vm.missing"));
+
+    let invalid = config.get_or::<Duration>("cache.size", Duration::ZERO).unwrap_err();
+    assert!(matches!(&invalid, ParseError::InvalidValue { key, value, .. } if key == "cache.size" && value == "16MiB"));
+    assert_eq!(
+        invalid.to_string(),
+        "Invalid value for key 'cache.size': '16MiB' (expected duration: unknown unit 'MiB')"
+    );
+}
+
+#[test]
+fn test_large_durations() {
+    assert_eq!(Duration::from_conf_value("5000000000ns").unwrap(), Duration::from_secs(5));
+    assert_eq!(Duration::from_conf_value("5000000000ms").unwrap(), Duration::from_secs(5_000_000));
+    assert_eq!(Duration::from_conf_value("1s500000000ns").unwrap(), Duration::from_millis(1500));
+    assert!(Duration::from_conf_value("18446744073709551615d").unwrap_err().contains("too large"));
+}